@@ -0,0 +1,187 @@
+use std::collections::HashMap;
+use std::fs;
+use sdl2::rect::{Point, Rect};
+use serde::Deserialize;
+
+// Sprite sheets and frame animations, described by toml files in assets/data.
+// Animations count in simulation ticks rather than real time so gameplay can rely on their events.
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Frame {
+	pub rect: [i32; 4],
+	pub ticks: usize,
+	pub pivot: [i32; 2],
+}
+
+impl Frame {
+	pub fn source_rect(&self) -> Rect {
+		Rect::new(self.rect[0], self.rect[1], self.rect[2].max(0) as u32, self.rect[3].max(0) as u32)
+	}
+	pub fn pivot(&self) -> Point {
+		Point::new(self.pivot[0], self.pivot[1])
+	}
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct AnimationEvent {
+	pub frame: usize,
+	pub name: String,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Clip {
+	pub frames: Vec<Frame>,
+	#[serde(default)]
+	pub looping: bool,
+	#[serde(default)]
+	pub events: Vec<AnimationEvent>,
+}
+
+impl Clip {
+	pub fn total_ticks(&self) -> usize {
+		self.frames.iter().map(|frame| frame.ticks.max(1)).sum()
+	}
+
+	fn local_tick(&self, tick: usize) -> usize {
+		//looping clips wrap around, the others hold their last frame
+		let total = self.total_ticks().max(1);
+		if self.looping { tick % total } else { tick.min(total - 1) }
+	}
+
+	fn frame_start(&self, frame_index: usize) -> usize {
+		self.frames.iter().take(frame_index).map(|frame| frame.ticks.max(1)).sum()
+	}
+
+	pub fn frame_index_at(&self, tick: usize) -> usize {
+		let mut local = self.local_tick(tick);
+		for (i, frame) in self.frames.iter().enumerate() {
+			if local < frame.ticks.max(1) {
+				return i;
+			}
+			local -= frame.ticks.max(1);
+		}
+		self.frames.len().saturating_sub(1)
+	}
+
+	pub fn frame_at(&self, tick: usize) -> Option<&Frame> {
+		self.frames.get(self.frame_index_at(tick))
+	}
+
+	pub fn event_tick(&self, name: &str) -> Option<usize> {
+		//the first tick an event fires on
+		self.events.iter()
+			.filter(|event| event.name == name && event.frame < self.frames.len())
+			.map(|event| self.frame_start(event.frame))
+			.min()
+	}
+
+	pub fn events_at(&self, tick: usize) -> impl Iterator<Item = &str> {
+		//events that fire on exactly this tick, ie their frame starts now
+		let local = if self.looping { self.local_tick(tick) } else { tick };
+		self.events.iter()
+			.filter(move |event| event.frame < self.frames.len() && self.frame_start(event.frame) == local)
+			.map(|event| event.name.as_str())
+	}
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct ClipId(usize);
+
+#[derive(Deserialize)]
+struct SpriteSheetFile {
+	texture: String,
+	clips: HashMap<String, Clip>,
+}
+
+#[derive(Debug)]
+pub struct SpriteSheet {
+	pub texture: String, //path of the sheet image, also its name in the asset manager
+	clips: Vec<Clip>,
+	clip_ids: HashMap<String, ClipId>,
+}
+
+impl SpriteSheet {
+	pub fn load(path: &str) -> Result<SpriteSheet, String> {
+		let text = fs::read_to_string(path).map_err(|why| format!("Unable to read sprite sheet {}: {}", path, why))?;
+		let file: SpriteSheetFile = toml::from_str(&text).map_err(|why| format!("Unable to parse sprite sheet {}: {}", path, why))?;
+		let mut clips = Vec::new();
+		let mut clip_ids = HashMap::new();
+		for (name, clip) in file.clips {
+			if clip.frames.is_empty() {
+				return Err(format!("Clip {} in {} has no frames", name, path));
+			}
+			clip_ids.insert(name, ClipId(clips.len()));
+			clips.push(clip);
+		}
+		Ok(SpriteSheet{ texture: file.texture, clips, clip_ids })
+	}
+
+	pub fn clip_id(&self, name: &str) -> Option<ClipId> {
+		self.clip_ids.get(name).copied()
+	}
+
+	pub fn clip(&self, id: ClipId) -> &Clip {
+		&self.clips[id.0]
+	}
+
+	pub fn directional_clip(&self, name: &str, facing_vector: f32) -> Option<(ClipId, bool)> {
+		//the clip for a facing angle and whether it needs mirroring
+		//looks for name_right/_up/_left/_down first, then falls back to name mirrored when facing left
+		let pi = std::f32::consts::PI;
+		let angle = facing_vector.rem_euclid(2.0 * pi);
+		let direction = match angle {
+			a if a < 0.25 * pi || a >= 1.75 * pi => "right",
+			a if a < 0.75 * pi => "up",
+			a if a < 1.25 * pi => "left",
+			_ => "down",
+		};
+		if let Some(id) = self.clip_id(&format!("{}_{}", name, direction)) {
+			return Some((id, false));
+		}
+		self.clip_id(name).map(|id| (id, facing_vector.cos() < 0.0))
+	}
+}
+
+// Where an actor is in its animation, small enough to live in the copyable battle state
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AnimationState {
+	pub clip: Option<ClipId>,
+	pub tick: usize,
+	pub flipped: bool,
+}
+
+impl AnimationState {
+	pub fn new() -> AnimationState {
+		AnimationState{ clip: None, tick: 0, flipped: false }
+	}
+
+	pub fn play(&mut self, clip: ClipId, flipped: bool) {
+		//advances a tick, or starts over if the clip changed
+		if self.clip == Some(clip) {
+			self.tick += 1;
+		} else {
+			self.clip = Some(clip);
+			self.tick = 0;
+		}
+		self.flipped = flipped;
+	}
+
+	pub fn play_at(&mut self, clip: ClipId, tick: usize, flipped: bool) {
+		//for states that already count their own frames
+		self.clip = Some(clip);
+		self.tick = tick;
+		self.flipped = flipped;
+	}
+
+	pub fn has_event(&self, sheet: &SpriteSheet, name: &str) -> bool {
+		//true on the tick the named event fires
+		match self.clip {
+			Some(clip) => sheet.clip(clip).events_at(self.tick).any(|event| event == name),
+			None => false,
+		}
+	}
+
+	pub fn current_frame<'a>(&self, sheet: &'a SpriteSheet) -> Option<&'a Frame> {
+		self.clip.and_then(|clip| sheet.clip(clip).frame_at(self.tick))
+	}
+}
@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use sdl2::rect::{Point, Rect};
+use serde::Deserialize;
+use crate::combat::{circle_hurts, overlaps};
+use crate::physics::Vec2;
+
+// Battle arenas, one toml file each in assets/data/arenas, named after the file.
+// An arena is the area actors can stand in plus the static shapes they can't walk through,
+// and where everyone starts out. Everything is in logical pixels.
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(tag = "shape", rename_all = "snake_case")]
+pub enum Obstacle{
+	Rect{ rect: [i32; 4] }, //x, y, width, height
+	Circle{ center: [i32; 2], radius: u32 },
+}
+
+impl Obstacle{
+	pub fn blocks(&self, area: Rect) -> bool{
+		match *self {
+			Obstacle::Rect{ rect } => overlaps(to_rect(rect), area),
+			Obstacle::Circle{ center, radius } => circle_hurts(Point::new(center[0], center[1]), radius, area),
+		}
+	}
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Spawn{
+	pub enemy: String,
+	pub position: [i32; 2],
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Arena{
+	bounds: [i32; 4],
+	pub player_start: [i32; 2],
+	#[serde(default)]
+	pub obstacles: Vec<Obstacle>,
+	#[serde(default)]
+	pub spawns: Vec<Spawn>,
+}
+
+impl Arena{
+	pub fn load(path: &Path) -> Result<Arena, String>{
+		let text = fs::read_to_string(path).map_err(|why| format!("Unable to read arena {}: {}", path.display(), why))?;
+		toml::from_str(&text).map_err(|why| format!("Unable to parse arena {}: {}", path.display(), why))
+	}
+
+	pub fn bounds(&self) -> Rect{
+		to_rect(self.bounds)
+	}
+
+	pub fn blocks(&self, area: Rect) -> bool{
+		//whether an actor or projectile covering area would be out of bounds or inside an obstacle
+		let bounds = self.bounds();
+		let inside = area.left() >= bounds.left() && area.right() <= bounds.right()
+			&& area.top() >= bounds.top() && area.bottom() <= bounds.bottom();
+		!inside || self.obstacles.iter().any(|obstacle| obstacle.blocks(area))
+	}
+
+	pub fn move_and_slide(&self, position: &mut Vec2, velocity: &mut Vec2, size: (u32, u32)){
+		//moves a box of the given size along its velocity as far as it can go without hitting anything
+		//one axis at a time, so running into a wall at an angle slides along it, and hitting one stops that axis dead
+		if let Some(x) = self.slide(*position, size, Vec2::new(velocity.x, 0.0)) {
+			position.x = x.x;
+			velocity.x = 0.0;
+		}else{
+			position.x += velocity.x;
+		}
+		if let Some(y) = self.slide(*position, size, Vec2::new(0.0, velocity.y)) {
+			position.y = y.y;
+			velocity.y = 0.0;
+		}else{
+			position.y += velocity.y;
+		}
+	}
+
+	fn slide(&self, position: Vec2, size: (u32, u32), delta: Vec2) -> Option<Vec2>{
+		//None if the whole move is fine, otherwise the furthest it gets before touching something
+		//something that's already stuck inside a wall is let out instead of being frozen there
+		let blocked = |at: Vec2| self.blocks(Rect::from_center(at.to_point(), size.0, size.1));
+		if !blocked(position + delta) || blocked(position) {
+			return None;
+		}
+		let (mut free, mut hit) = (0.0, 1.0);
+		for _ in 0..8 {
+			let half = (free + hit) / 2.0;
+			if blocked(position + delta * half) { hit = half } else { free = half }
+		}
+		Some(position + delta * free)
+	}
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct ArenaId(usize);
+
+#[derive(Debug)]
+pub struct Arenas{
+	arenas: Vec<Arena>,
+	ids: HashMap<String, ArenaId>,
+}
+
+impl Arenas{
+	pub fn load_dir(dir: &str) -> Result<Arenas, String>{
+		let mut paths: Vec<_> = fs::read_dir(dir)
+			.map_err(|why| format!("Unable to read arenas directory {}: {}", dir, why))?
+			.filter_map(|entry| entry.ok().map(|entry| entry.path()))
+			.filter(|path| path.extension().is_some_and(|extension| extension == "toml"))
+			.collect();
+		paths.sort();
+		let mut arenas = Arenas{ arenas: Vec::new(), ids: HashMap::new() };
+		for path in paths {
+			let name = path.file_stem().unwrap().to_string_lossy().to_string();
+			arenas.ids.insert(name, ArenaId(arenas.arenas.len()));
+			arenas.arenas.push(Arena::load(&path)?);
+		}
+		Ok(arenas)
+	}
+
+	pub fn id(&self, name: &str) -> Option<ArenaId>{
+		self.ids.get(name).copied()
+	}
+
+	pub fn get(&self, id: ArenaId) -> &Arena{
+		&self.arenas[id.0]
+	}
+
+	pub fn named(&self) -> impl Iterator<Item = (&String, &Arena)>{
+		self.ids.iter().map(|(name, id)| (name, &self.arenas[id.0]))
+	}
+}
+
+pub fn to_rect(rect: [i32; 4]) -> Rect{
+	Rect::new(rect[0], rect[1], rect[2].max(0) as u32, rect[3].max(0) as u32)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn walled_arena() -> Arena{
+		//a wall down the middle of a 200x200 arena
+		Arena{
+			bounds: [0, 0, 200, 200],
+			player_start: [50, 100],
+			obstacles: vec![Obstacle::Rect{ rect: [100, 0, 20, 200] }],
+			spawns: Vec::new(),
+		}
+	}
+
+	#[test]
+	fn slides_along_a_wall(){
+		let arena = walled_arena();
+		let (mut position, mut velocity) = (Vec2::new(80.0, 100.0), Vec2::new(20.0, 5.0));
+		arena.move_and_slide(&mut position, &mut velocity, (10, 10));
+		//stopped up against the wall but still moving along it, positions round to whole pixels for the check
+		assert!(position.x >= 94.5 && position.x < 95.5, "x was {}", position.x);
+		assert_eq!(velocity.x, 0.0);
+		assert_eq!(position.y, 105.0);
+		assert_eq!(velocity.y, 5.0);
+	}
+
+	#[test]
+	fn lets_things_stuck_in_a_wall_out(){
+		let arena = walled_arena();
+		let (mut position, mut velocity) = (Vec2::new(110.0, 100.0), Vec2::new(-10.0, 0.0));
+		arena.move_and_slide(&mut position, &mut velocity, (10, 10));
+		assert_eq!(position, Vec2::new(100.0, 100.0));
+		assert_eq!(velocity, Vec2::new(-10.0, 0.0));
+	}
+
+	#[test]
+	fn stops_at_the_bounds(){
+		let arena = walled_arena();
+		let (mut position, mut velocity) = (Vec2::new(20.0, 8.0), Vec2::new(0.0, -10.0));
+		arena.move_and_slide(&mut position, &mut velocity, (10, 10));
+		assert!(position.y >= 4.5 && position.y < 5.5, "y was {}", position.y);
+		assert_eq!(velocity, Vec2::ZERO);
+	}
+}
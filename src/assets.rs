@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use sdl2::image::LoadTexture;
+use sdl2::pixels::Color;
+use sdl2::render::{Texture, TextureCreator};
+use sdl2::ttf::{Font, Sdl2TtfContext};
+use sdl2::video::WindowContext;
+use crate::game_data::GameData;
+use crate::sound_manager::SoundManager;
+
+// Owns every font and texture the screens draw with, so nothing gets loaded from disk mid-frame.
+// Assets are loaded once by name and handed out as handles, rendered text is cached as textures
+// and thrown away once it hasn't been drawn for a while.
+
+const TEXT_CACHE_FRAMES: u64 = 120; //how many frames a rendered string can go undrawn before it's freed
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct FontHandle(usize);
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct TextureHandle(usize);
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct TextKey {
+	font: FontHandle,
+	size: u16,
+	outline: u16,
+	text: String,
+	color: Color,
+}
+
+struct CachedText<'a> {
+	texture: Texture<'a>,
+	last_used: u64,
+}
+
+pub struct AssetManager<'a> {
+	texture_creator: &'a TextureCreator<WindowContext>,
+	ttf_context: &'a Sdl2TtfContext,
+	font_paths: Vec<String>,
+	font_names: HashMap<String, FontHandle>,
+	fonts: HashMap<(FontHandle, u16, u16), Font<'a, 'static>>, //each font is opened once per point size and outline width it's drawn at
+	textures: Vec<Texture<'a>>,
+	texture_names: HashMap<String, TextureHandle>,
+	text_cache: HashMap<TextKey, CachedText<'a>>,
+	frame: u64,
+}
+
+impl<'a> AssetManager<'a> {
+	pub fn new(texture_creator: &'a TextureCreator<WindowContext>, ttf_context: &'a Sdl2TtfContext) -> AssetManager<'a> {
+		AssetManager {
+			texture_creator,
+			ttf_context,
+			font_paths: Vec::new(),
+			font_names: HashMap::new(),
+			fonts: HashMap::new(),
+			textures: Vec::new(),
+			texture_names: HashMap::new(),
+			text_cache: HashMap::new(),
+			frame: 0,
+		}
+	}
+
+	pub fn load_defaults(&mut self, data: &GameData, sound_manager: &mut SoundManager) -> Result<(), String> {
+		//everything the game needs at startup
+		self.load_font("frontman", "assets/fonts/The_Frontman.ttf")?;
+		self.load_texture("background", "assets/images/neon_noire.png")?;
+		//sprite sheets are named by their image path so the data files can refer to them directly
+		let player_sheet = &data.player_sprites.texture;
+		self.load_texture(player_sheet, player_sheet)?;
+		sound_manager.preload_sound_effects()?;
+		Ok(())
+	}
+
+	pub fn load_font(&mut self, name: &str, path: &str) -> Result<FontHandle, String> {
+		//open it once now so a bad path fails at startup instead of the first time it's drawn
+		let handle = FontHandle(self.font_paths.len());
+		let font = self.ttf_context.load_font(path, 16)
+			.map_err(|why| format!("Unable to load font {}: {}", path, why))?;
+		self.font_paths.push(path.to_string());
+		self.fonts.insert((handle, 16, 0), font);
+		self.font_names.insert(name.to_string(), handle);
+		Ok(handle)
+	}
+
+	pub fn load_texture(&mut self, name: &str, path: &str) -> Result<TextureHandle, String> {
+		let texture = self.texture_creator.load_texture(path)
+			.map_err(|why| format!("Unable to load texture {}: {}", path, why))?;
+		let handle = TextureHandle(self.textures.len());
+		self.textures.push(texture);
+		self.texture_names.insert(name.to_string(), handle);
+		Ok(handle)
+	}
+
+	pub fn font(&self, name: &str) -> Option<FontHandle> {
+		self.font_names.get(name).copied()
+	}
+
+	pub fn texture(&self, name: &str) -> Option<TextureHandle> {
+		self.texture_names.get(name).copied()
+	}
+
+	pub fn get_texture(&self, handle: TextureHandle) -> &Texture<'a> {
+		&self.textures[handle.0]
+	}
+
+	pub fn sized_font(&mut self, font: FontHandle, size: u16, outline: u16) -> Result<&Font<'a, 'static>, String> {
+		//outline is the width of the outline in pixels, 0 for the regular glyphs
+		if !self.fonts.contains_key(&(font, size, outline)) {
+			let path = &self.font_paths[font.0];
+			let mut loaded = self.ttf_context.load_font(path, size)
+				.map_err(|why| format!("Unable to load font {} at size {}: {}", path, size, why))?;
+			loaded.set_outline_width(outline);
+			self.fonts.insert((font, size, outline), loaded);
+		}
+		Ok(&self.fonts[&(font, size, outline)])
+	}
+
+	pub fn outlined_text(&mut self, font: FontHandle, size: u16, outline: u16, text: &str, color: Color) -> Result<&Texture<'a>, String> {
+		//a texture of the string, only rasterized the first time this exact text is asked for
+		let key = TextKey{ font, size, outline, text: text.to_string(), color };
+		if !self.text_cache.contains_key(&key) {
+			let texture_creator = self.texture_creator;
+			let surface = self.sized_font(font, size, outline)?
+				.render(text)
+				.blended(color)
+				.map_err(|why| format!("Unable to render text {}: {}", text, why))?;
+			let texture = texture_creator.create_texture_from_surface(&surface)
+				.map_err(|why| format!("Unable to create texture for text {}: {}", text, why))?;
+			self.text_cache.insert(key.clone(), CachedText{ texture, last_used: self.frame });
+		}
+		let cached = self.text_cache.get_mut(&key).unwrap();
+		cached.last_used = self.frame;
+		Ok(&cached.texture)
+	}
+
+	pub fn end_frame(&mut self) {
+		//call once per rendered frame, frees text that hasn't been drawn in a while
+		self.frame += 1;
+		let frame = self.frame;
+		self.text_cache.retain(|_, cached| frame - cached.last_used <= TEXT_CACHE_FRAMES);
+	}
+}
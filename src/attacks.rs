@@ -0,0 +1,222 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use serde::Deserialize;
+
+// The player's attacks and the chains they make, loaded from assets/data/attacks.toml.
+// An attack plays its clip stretched over a set number of ticks, its hitbox is out from hit_start up to hit_end.
+// From its cancel window on, a buffered press that matches one of its links cuts straight into the next attack.
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum AttackInput{
+	Light,
+	Heavy,
+}
+
+#[derive(Deserialize)]
+struct LinkDef{
+	input: AttackInput,
+	attack: String,
+}
+
+#[derive(Deserialize)]
+struct AttackDef{
+	clip: String,
+	ticks: usize,
+	hit_start: usize,
+	hit_end: usize,
+	cancel_start: usize,
+	cost: f32,
+	reach: f32,
+	size: u32,
+	damage: i32,
+	knockback_ticks: usize,
+	#[serde(default)]
+	lunge: f32,
+	#[serde(default)]
+	next: Vec<LinkDef>,
+}
+
+#[derive(Deserialize)]
+struct OpenersDef{
+	ground: Vec<LinkDef>,
+	#[serde(default)]
+	dash: Vec<LinkDef>,
+}
+
+#[derive(Deserialize)]
+struct AttacksFile{
+	buffer_ticks: usize,
+	openers: OpenersDef,
+	attacks: BTreeMap<String, AttackDef>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Link{
+	pub input: AttackInput,
+	pub attack: AttackId,
+}
+
+#[derive(Clone, Debug)]
+pub struct Attack{
+	pub clip: String,
+	pub ticks: usize,
+	pub hit_start: usize, //the hitbox is out from this tick until hit_end
+	pub hit_end: usize,
+	pub cancel_start: usize, //the tick from which a link can cancel the rest of the attack
+	pub cost: f32, //stamina
+	pub reach: f32,
+	pub size: u32,
+	pub damage: i32,
+	pub knockback_ticks: usize,
+	pub lunge: f32, //speed the player is carried forward at while attacking
+	pub next: Vec<Link>,
+}
+
+impl Attack{
+	pub fn hits(&self, framecount: usize) -> bool{
+		(self.hit_start..self.hit_end).contains(&framecount)
+	}
+
+	pub fn can_cancel(&self, framecount: usize) -> bool{
+		framecount >= self.cancel_start
+	}
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct AttackId(usize);
+
+#[derive(Debug)]
+pub struct Attacks{
+	attacks: Vec<Attack>,
+	pub buffer_ticks: usize, //how long a press is remembered
+	pub ground_openers: Vec<Link>, //attacks that start from standing or running
+	pub dash_openers: Vec<Link>, //attacks that cancel a dash
+}
+
+impl Attacks{
+	pub fn load(path: &str) -> Result<Attacks, String>{
+		let text = fs::read_to_string(path).map_err(|why| format!("Unable to read attacks {}: {}", path, why))?;
+		Attacks::parse(&text, path)
+	}
+
+	fn parse(text: &str, path: &str) -> Result<Attacks, String>{
+		let file: AttacksFile = toml::from_str(text).map_err(|why| format!("Unable to parse attacks {}: {}", path, why))?;
+		//every attack gets its id before any links are resolved, so links can point at attacks further down the file
+		let ids: HashMap<String, AttackId> = file.attacks.keys().enumerate()
+			.map(|(i, name)| (name.clone(), AttackId(i)))
+			.collect();
+		let resolve = |links: &[LinkDef]| -> Result<Vec<Link>, String>{
+			links.iter().map(|link| match ids.get(&link.attack) {
+				Some(id) => Ok(Link{ input: link.input, attack: *id }),
+				None => Err(format!("No attack named {} in {}", link.attack, path)),
+			}).collect()
+		};
+		let mut attacks = Vec::new();
+		for (name, def) in &file.attacks {
+			if def.hit_start > def.hit_end || def.hit_end > def.ticks {
+				return Err(format!("Attack {} in {} has its hit outside of its {} ticks", name, path, def.ticks));
+			}
+			attacks.push(Attack{
+				clip: def.clip.clone(),
+				ticks: def.ticks,
+				hit_start: def.hit_start,
+				hit_end: def.hit_end,
+				cancel_start: def.cancel_start,
+				cost: def.cost,
+				reach: def.reach,
+				size: def.size,
+				damage: def.damage,
+				knockback_ticks: def.knockback_ticks,
+				lunge: def.lunge,
+				next: resolve(&def.next)?,
+			});
+		}
+		Ok(Attacks{
+			buffer_ticks: file.buffer_ticks,
+			ground_openers: resolve(&file.openers.ground)?,
+			dash_openers: resolve(&file.openers.dash)?,
+			attacks,
+		})
+	}
+
+	pub fn get(&self, id: AttackId) -> &Attack{
+		&self.attacks[id.0]
+	}
+}
+
+pub fn find_link(links: &[Link], input: AttackInput) -> Option<AttackId>{
+	links.iter().find(|link| link.input == input).map(|link| link.attack)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const ATTACKS: &str = r#"
+buffer_ticks = 10
+
+[openers]
+ground = [{ input = "light", attack = "slash" }]
+
+[attacks.slash]
+clip = "attacking"
+ticks = 30
+hit_start = 6
+hit_end = 20
+cancel_start = 16
+cost = 15.0
+reach = 35.0
+size = 50
+damage = 10
+knockback_ticks = 10
+next = [{ input = "heavy", attack = "smash" }]
+
+[attacks.smash]
+clip = "attacking"
+ticks = 40
+hit_start = 8
+hit_end = 30
+cancel_start = 40
+cost = 25.0
+reach = 40.0
+size = 60
+damage = 20
+knockback_ticks = 16
+"#;
+
+	#[test]
+	fn links_resolve_to_attacks_further_down_the_file(){
+		let attacks = Attacks::parse(ATTACKS, "test").unwrap();
+		let slash = attacks.get(find_link(&attacks.ground_openers, AttackInput::Light).unwrap());
+		let smash = attacks.get(find_link(&slash.next, AttackInput::Heavy).unwrap());
+		assert_eq!(smash.damage, 20);
+		assert!(find_link(&slash.next, AttackInput::Light).is_none());
+	}
+
+	#[test]
+	fn an_unknown_link_is_an_error(){
+		let text = ATTACKS.replace(r#"attack = "smash""#, r#"attack = "uppercut""#);
+		let error = Attacks::parse(&text, "test").unwrap_err();
+		assert_eq!(error, "No attack named uppercut in test");
+	}
+
+	#[test]
+	fn a_hit_past_the_end_is_an_error(){
+		let text = ATTACKS.replace("hit_end = 30", "hit_end = 41");
+		assert!(Attacks::parse(&text, "test").is_err());
+	}
+
+	#[test]
+	fn hits_and_cancels_within_their_windows(){
+		let attacks = Attacks::parse(ATTACKS, "test").unwrap();
+		let slash = attacks.get(find_link(&attacks.ground_openers, AttackInput::Light).unwrap());
+		assert!(!slash.hits(5));
+		assert!(slash.hits(6));
+		assert!(slash.hits(19));
+		assert!(!slash.hits(20));
+		assert!(!slash.can_cancel(15));
+		assert!(slash.can_cancel(16));
+		assert!(slash.can_cancel(30));
+	}
+}
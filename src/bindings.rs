@@ -0,0 +1,250 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::PathBuf;
+use sdl2::keyboard::Keycode;
+use sdl2::controller::{Axis, Button};
+use serde::{Deserialize, Serialize};
+use crate::input::{InputButton, InputAxis};
+
+// Maps physical keys, gamepad buttons and gamepad axes onto the logical fields of InputState.
+// Saved as toml in the user config directory, keyed by logical input so one action can have several bindings:
+//   [keyboard]
+//   DpadUp = ["W", "Up"]
+//   [gamepad_buttons]
+//   Down = ["a"]
+//   [gamepad_axes]
+//   LeftX = ["leftx"]
+// Key names are SDL key names, gamepad names are SDL game controller names.
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Bindings{
+	#[serde(default)]
+	pub keyboard: BTreeMap<InputButton, Vec<String>>,
+	#[serde(default)]
+	pub gamepad_buttons: BTreeMap<InputButton, Vec<String>>,
+	#[serde(default)]
+	pub gamepad_axes: BTreeMap<InputAxis, Vec<String>>,
+
+	//reverse lookups built from the tables above, rebuilt whenever they change
+	#[serde(skip)]
+	key_lookup: HashMap<Keycode, Vec<InputButton>>,
+	#[serde(skip)]
+	button_lookup: HashMap<Button, Vec<InputButton>>,
+	#[serde(skip)]
+	axis_lookup: HashMap<Axis, Vec<InputAxis>>,
+}
+
+impl Bindings{
+	pub fn new() -> Bindings{
+		let mut bindings = Bindings{
+			keyboard: BTreeMap::new(),
+			gamepad_buttons: BTreeMap::new(),
+			gamepad_axes: BTreeMap::new(),
+			key_lookup: HashMap::new(),
+			button_lookup: HashMap::new(),
+			axis_lookup: HashMap::new(),
+		};
+		let keyboard = [
+			(InputButton::DpadUp, Keycode::W),
+			(InputButton::DpadLeft, Keycode::A),
+			(InputButton::DpadRight, Keycode::D),
+			(InputButton::DpadDown, Keycode::S),
+			(InputButton::Up, Keycode::I),
+			(InputButton::Left, Keycode::J),
+			(InputButton::Down, Keycode::K),
+			(InputButton::Right, Keycode::L),
+			(InputButton::LeftShoulder, Keycode::E),
+			(InputButton::RightShoulder, Keycode::U),
+			(InputButton::Start, Keycode::Return),
+		];
+		for (logical, key) in keyboard {
+			bindings.keyboard.entry(logical).or_default().push(key.name());
+		}
+		let gamepad_buttons = [
+			(InputButton::Down, Button::A),
+			(InputButton::Left, Button::X),
+			(InputButton::Up, Button::Y),
+			(InputButton::Right, Button::B),
+			(InputButton::LeftShoulder, Button::LeftShoulder),
+			(InputButton::RightShoulder, Button::RightShoulder),
+			(InputButton::DpadDown, Button::DPadDown),
+			(InputButton::DpadLeft, Button::DPadLeft),
+			(InputButton::DpadRight, Button::DPadRight),
+			(InputButton::DpadUp, Button::DPadUp),
+			(InputButton::Start, Button::Start),
+			(InputButton::Back, Button::Back),
+			(InputButton::LeftStick, Button::LeftStick),
+			(InputButton::RightStick, Button::RightStick),
+		];
+		for (logical, button) in gamepad_buttons {
+			bindings.gamepad_buttons.entry(logical).or_default().push(button.string());
+		}
+		let gamepad_axes = [
+			(InputAxis::LeftX, Axis::LeftX),
+			(InputAxis::LeftY, Axis::LeftY),
+			(InputAxis::RightX, Axis::RightX),
+			(InputAxis::RightY, Axis::RightY),
+			(InputAxis::TriggerLeft, Axis::TriggerLeft),
+			(InputAxis::TriggerRight, Axis::TriggerRight),
+		];
+		for (logical, axis) in gamepad_axes {
+			bindings.gamepad_axes.entry(logical).or_default().push(axis.string());
+		}
+		bindings.rebuild_lookup();
+		bindings
+	}
+
+	pub fn config_path() -> Option<PathBuf>{
+		dirs::config_dir().map(|dir| dir.join("game2").join("bindings.toml"))
+	}
+
+	pub fn load_or_default() -> Bindings{
+		//missing or broken config files shouldn't stop the game from starting, just use the defaults
+		let path = match Bindings::config_path() {
+			Some(path) if path.exists() => path,
+			_ => return Bindings::new(),
+		};
+		match Bindings::load(&path) {
+			Ok(bindings) => bindings,
+			Err(why) => {
+				println!("{}, using default bindings", why);
+				Bindings::new()
+			}
+		}
+	}
+
+	pub fn load(path: &PathBuf) -> Result<Bindings, String>{
+		let text = fs::read_to_string(path)
+			.map_err(|why| format!("Unable to read bindings {}: {}", path.display(), why))?;
+		let mut bindings: Bindings = toml::from_str(&text)
+			.map_err(|why| format!("Unable to parse bindings {}: {}", path.display(), why))?;
+		bindings.rebuild_lookup();
+		Ok(bindings)
+	}
+
+	pub fn save(&self) -> Result<(), String>{
+		let path = Bindings::config_path().ok_or("Unable to find the user config directory")?;
+		if let Some(dir) = path.parent() {
+			fs::create_dir_all(dir).map_err(|why| format!("Unable to create {}: {}", dir.display(), why))?;
+		}
+		let text = toml::to_string_pretty(self).map_err(|why| format!("Unable to serialize bindings: {}", why))?;
+		fs::write(&path, text).map_err(|why| format!("Unable to write bindings {}: {}", path.display(), why))
+	}
+
+	pub fn bind_key(&mut self, logical: InputButton, key: Keycode){
+		let names = self.keyboard.entry(logical).or_default();
+		if !names.contains(&key.name()) {
+			names.push(key.name());
+		}
+		self.rebuild_lookup();
+	}
+
+	pub fn bind_gamepad_button(&mut self, logical: InputButton, button: Button){
+		let names = self.gamepad_buttons.entry(logical).or_default();
+		if !names.contains(&button.string()) {
+			names.push(button.string());
+		}
+		self.rebuild_lookup();
+	}
+
+	pub fn bind_gamepad_axis(&mut self, logical: InputAxis, axis: Axis){
+		let names = self.gamepad_axes.entry(logical).or_default();
+		if !names.contains(&axis.string()) {
+			names.push(axis.string());
+		}
+		self.rebuild_lookup();
+	}
+
+	pub fn clear_bindings(&mut self, logical: InputButton){
+		//removes every key and gamepad button bound to this input, usually followed by binding a new one
+		self.keyboard.remove(&logical);
+		self.gamepad_buttons.remove(&logical);
+		self.rebuild_lookup();
+	}
+
+	pub fn clear_axis_bindings(&mut self, logical: InputAxis){
+		self.gamepad_axes.remove(&logical);
+		self.rebuild_lookup();
+	}
+
+	pub fn buttons_for_key(&self, key: Keycode) -> &[InputButton]{
+		self.key_lookup.get(&key).map(|buttons| buttons.as_slice()).unwrap_or(&[])
+	}
+
+	pub fn buttons_for_gamepad_button(&self, button: Button) -> &[InputButton]{
+		self.button_lookup.get(&button).map(|buttons| buttons.as_slice()).unwrap_or(&[])
+	}
+
+	pub fn axes_for_gamepad_axis(&self, axis: Axis) -> &[InputAxis]{
+		self.axis_lookup.get(&axis).map(|axes| axes.as_slice()).unwrap_or(&[])
+	}
+
+	fn rebuild_lookup(&mut self){
+		self.key_lookup.clear();
+		self.button_lookup.clear();
+		self.axis_lookup.clear();
+		for (logical, names) in &self.keyboard {
+			for name in names {
+				match Keycode::from_name(name) {
+					Some(key) => self.key_lookup.entry(key).or_default().push(*logical),
+					None => println!("Unknown key {} bound to {:?}", name, logical),
+				}
+			}
+		}
+		for (logical, names) in &self.gamepad_buttons {
+			for name in names {
+				match Button::from_string(name) {
+					Some(button) => self.button_lookup.entry(button).or_default().push(*logical),
+					None => println!("Unknown gamepad button {} bound to {:?}", name, logical),
+				}
+			}
+		}
+		for (logical, names) in &self.gamepad_axes {
+			for name in names {
+				match Axis::from_string(name) {
+					Some(axis) => self.axis_lookup.entry(axis).or_default().push(*logical),
+					None => println!("Unknown gamepad axis {} bound to {:?}", name, logical),
+				}
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn unbound() -> Bindings{
+		Bindings{
+			keyboard: BTreeMap::new(),
+			gamepad_buttons: BTreeMap::new(),
+			gamepad_axes: BTreeMap::new(),
+			key_lookup: HashMap::new(),
+			button_lookup: HashMap::new(),
+			axis_lookup: HashMap::new(),
+		}
+	}
+
+	#[test]
+	fn several_bindings_per_input_survive_toml(){
+		let mut bindings = unbound();
+		bindings.keyboard.insert(InputButton::DpadUp, vec!["W".to_string(), "Up".to_string()]);
+		bindings.keyboard.insert(InputButton::Start, vec!["Return".to_string()]);
+		bindings.gamepad_buttons.insert(InputButton::Down, vec!["a".to_string(), "leftshoulder".to_string()]);
+		bindings.gamepad_axes.insert(InputAxis::LeftX, vec!["leftx".to_string(), "rightx".to_string()]);
+		let text = toml::to_string_pretty(&bindings).unwrap();
+		let loaded: Bindings = toml::from_str(&text).unwrap();
+		assert_eq!(loaded.keyboard, bindings.keyboard);
+		assert_eq!(loaded.gamepad_buttons, bindings.gamepad_buttons);
+		assert_eq!(loaded.gamepad_axes, bindings.gamepad_axes);
+	}
+
+	#[test]
+	fn reads_tables_keyed_by_input_name(){
+		let text = "[keyboard]\nDpadUp = [\"W\", \"Up\"]\n[gamepad_axes]\nTriggerLeft = [\"lefttrigger\"]\n";
+		let loaded: Bindings = toml::from_str(text).unwrap();
+		assert_eq!(loaded.keyboard[&InputButton::DpadUp], vec!["W", "Up"]);
+		assert_eq!(loaded.gamepad_axes[&InputAxis::TriggerLeft], vec!["lefttrigger"]);
+		assert!(loaded.gamepad_buttons.is_empty());
+	}
+}
@@ -0,0 +1,193 @@
+use sdl2::rect::{Point, Rect};
+use serde::Deserialize;
+use crate::physics::{MovementStats, Vec2};
+
+// Damage resolution shared by everything that fights in a battle.
+// Attacks produce hits during the tick, then the battle resolves them all at once so the order
+// actors update in doesn't decide who gets hit first.
+
+const KNOCKBACK_SPEED: f32 = 6.0;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Target{
+	Player,
+	Enemy(usize), //index into BattleContext::enemies
+}
+
+// Lingering effects a hit can leave on its target
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(tag = "effect", rename_all = "snake_case")]
+pub enum StatusEffect{
+	Burn{ damage: i32, interval: usize }, //damage every interval ticks, ignores invulnerability
+	Slow{ factor: f32 }, //movement speed multiplier
+	Stun,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct Status{
+	#[serde(flatten)]
+	pub effect: StatusEffect,
+	pub ticks: usize, //how long it lasts, counts down once applied
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Hitbox{
+	pub rect: Rect,
+	pub damage: i32,
+	pub knockback_ticks: usize,
+	pub status: Option<Status>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Hit{
+	pub attacker: Target,
+	pub target: Target,
+	pub damage: i32,
+	pub knockback_vector: f32, //the angle the target gets pushed along, same convention as facing_vector
+	pub knockback_ticks: usize,
+	pub status: Option<Status>,
+}
+
+// Health and hit reactions, one for each actor that can take damage
+#[derive(Clone, Copy, Debug)]
+pub struct Vitals{
+	pub health: i32,
+	pub max_health: i32,
+	pub invulnerable_ticks: usize, //hits are ignored while this counts down
+	pub invulnerability: usize, //how many ticks of invulnerability taking a hit gives
+	pub knockback_ticks: usize,
+	pub knockback_vector: f32,
+	pub status: Option<Status>, //one at a time, a new one replaces the old
+}
+
+impl Vitals{
+	pub fn new(max_health: i32, invulnerability: usize) -> Vitals{
+		Vitals{
+			health: max_health,
+			max_health,
+			invulnerable_ticks: 0,
+			invulnerability,
+			knockback_ticks: 0,
+			knockback_vector: 0.0,
+			status: None,
+		}
+	}
+
+	pub fn is_dead(&self) -> bool{
+		self.health <= 0
+	}
+
+	pub fn is_invulnerable(&self) -> bool{
+		self.invulnerable_ticks > 0
+	}
+
+	pub fn take_hit(&mut self, hit: &Hit) -> bool{
+		//returns whether the hit landed
+		if self.is_invulnerable() || self.is_dead() {
+			return false;
+		}
+		self.health = (self.health - hit.damage).max(0);
+		self.invulnerable_ticks = self.invulnerability;
+		self.knock_back(hit.knockback_vector, hit.knockback_ticks);
+		if hit.status.is_some() {
+			self.status = hit.status;
+		}
+		true
+	}
+
+	pub fn take_blocked_hit(&mut self, damage: i32){
+		//what gets through a block only hurts, there's no knockback or status
+		self.health = (self.health - damage).max(0);
+		self.invulnerable_ticks = self.invulnerability;
+	}
+
+	pub fn knock_back(&mut self, knockback_vector: f32, ticks: usize){
+		self.knockback_ticks = ticks;
+		self.knockback_vector = knockback_vector;
+	}
+
+	pub fn is_stunned(&self) -> bool{
+		matches!(self.status, Some(Status{ effect: StatusEffect::Stun, .. }))
+	}
+
+	pub fn speed_multiplier(&self) -> f32{
+		match self.status {
+			Some(Status{ effect: StatusEffect::Slow{ factor }, .. }) => factor,
+			_ => 1.0
+		}
+	}
+
+	fn tick_status(&mut self){
+		if let Some(status) = self.status.as_mut() {
+			if let StatusEffect::Burn{ damage, interval } = status.effect {
+				if status.ticks % interval.max(1) == 0 {
+					self.health = (self.health - damage).max(0);
+				}
+			}
+			status.ticks = status.ticks.saturating_sub(1);
+			if status.ticks == 0 {
+				self.status = None;
+			}
+		}
+	}
+
+	pub fn tick(&mut self, velocity: &mut Vec2, movement: &MovementStats) -> bool{
+		//counts down invulnerability and status effects, and takes over the actor's velocity while it's knocked back
+		//returns true while knocked back or stunned, the actor shouldn't act on its own during that
+		self.invulnerable_ticks = self.invulnerable_ticks.saturating_sub(1);
+		let stunned = self.is_stunned();
+		self.tick_status();
+		if self.knockback_ticks > 0 {
+			self.knockback_ticks -= 1;
+			*velocity = Vec2::from_angle(self.knockback_vector, KNOCKBACK_SPEED);
+			return true;
+		}
+		if stunned {
+			//slides to a stop instead of freezing mid-step
+			*velocity = movement.drift(*velocity);
+		}
+		stunned
+	}
+}
+
+pub fn hurtbox(position: Point, width: u32, height: u32) -> Rect{
+	Rect::from_center(position, width, height)
+}
+
+pub fn attack_hitbox(position: Point, facing_vector: f32, reach: f32, size: u32) -> Rect{
+	//a square in front of the attacker
+	let center = Point::new(
+		position.x + (facing_vector.cos() * reach) as i32,
+		position.y - (facing_vector.sin() * reach) as i32
+	);
+	Rect::from_center(center, size, size)
+}
+
+pub fn angle_between(from: Point, to: Point) -> f32{
+	//the facing_vector that points from one position to another, y is flipped because screen y grows downwards
+	((from.y - to.y) as f32).atan2((to.x - from.x) as f32)
+}
+
+pub fn overlaps(a: Rect, b: Rect) -> bool{
+	//done here instead of Rect::has_intersection, which calls into SDL, so headless runs don't need it
+	a.left() < b.right() && b.left() < a.right() && a.top() < b.bottom() && b.top() < a.bottom()
+}
+
+pub fn circle_hurts(center: Point, radius: u32, hurtbox: Rect) -> bool{
+	//whether a circle touches a hurtbox, for projectiles and explosions
+	let closest_x = center.x.clamp(hurtbox.left(), hurtbox.right());
+	let closest_y = center.y.clamp(hurtbox.top(), hurtbox.bottom());
+	let (dx, dy) = ((center.x - closest_x) as i64, (center.y - closest_y) as i64);
+	dx * dx + dy * dy <= (radius as i64) * (radius as i64)
+}
+
+pub fn check_hit(attacker: Target, attacker_position: Point, hitbox: &Hitbox, target: Target, hurtbox: Rect) -> Option<Hit>{
+	overlaps(hitbox.rect, hurtbox).then(|| Hit{
+		attacker,
+		target,
+		damage: hitbox.damage,
+		knockback_vector: angle_between(attacker_position, hurtbox.center()),
+		knockback_ticks: hitbox.knockback_ticks,
+		status: hitbox.status,
+	})
+}
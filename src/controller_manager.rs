@@ -0,0 +1,60 @@
+use std::collections::HashMap;
+use sdl2::controller::GameController;
+use sdl2::event::Event;
+use sdl2::GameControllerSubsystem;
+use crate::input::{InputState, ControllerType};
+
+// Opens game controllers as they're plugged in and releases them when they're unplugged.
+// SDL sends a ControllerDeviceAdded event for every pad already connected at startup, so
+// starting with no pads open is fine, and starting with no pads at all just leaves us on the keyboard.
+pub struct ControllerManager{
+	subsystem: GameControllerSubsystem,
+	controllers: HashMap<u32, GameController>, //keyed by SDL instance id
+	active: Option<u32>, //the pad the player last used
+}
+
+impl ControllerManager{
+	pub fn new(subsystem: GameControllerSubsystem) -> ControllerManager{
+		ControllerManager{
+			subsystem,
+			controllers: HashMap::new(),
+			active: None,
+		}
+	}
+
+	pub fn handle_event(&mut self, input: &mut InputState, event: &Event){
+		match event {
+			Event::ControllerDeviceAdded { which: joystick_index, .. } => {
+				match self.subsystem.open(*joystick_index) {
+					Ok(controller) => {
+						println!("Controller added: {}", controller.name());
+						let instance_id = controller.instance_id();
+						if self.active.is_none() {
+							self.active = Some(instance_id);
+						}
+						self.controllers.insert(instance_id, controller);
+					},
+					Err(why) => println!("Unable to open controller {}: {}", joystick_index, why),
+				}
+			},
+			Event::ControllerDeviceRemoved { which: instance_id, .. } => {
+				if let Some(controller) = self.controllers.remove(instance_id) {
+					println!("Controller removed: {}", controller.name());
+				}
+				if self.active == Some(*instance_id) {
+					//whatever the pad was holding would be stuck down forever, so let go of everything
+					self.active = None;
+					input.release_all();
+					input.device = ControllerType::Keyboard;
+					input.controller_disconnected = true;
+				}
+			},
+			Event::ControllerAxisMotion { which, .. }
+			| Event::ControllerButtonDown { which, .. }
+			| Event::ControllerButtonUp { which, .. } => {
+				self.active = Some(*which);
+			},
+			_ => ()
+		}
+	}
+}
@@ -0,0 +1,148 @@
+use sdl2::rect::Rect;
+use crate::arena::Arena;
+use crate::combat::{attack_hitbox, hurtbox, Hitbox, Vitals};
+use crate::enemy_ai::{Action, Condition, EnemyKind, EnemyType, EnemyTypes};
+use crate::physics::Vec2;
+use crate::screens::battle::{BattlePlayerContext, PlayerState};
+
+// Enemies in a battle. Like the player they live in the copyable battle state,
+// so an enemy only carries its type and where it is in that type's behaviour, the behaviour itself is data.
+
+pub const MAX_ENEMIES: usize = 8;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Enemy{
+	pub kind: EnemyKind,
+	pub position: Vec2,
+	pub velocity: Vec2,
+	pub facing_vector: f32,
+	pub behaviour: usize, //index of the current state in the type's states
+	pub state_ticks: usize, //ticks spent in the current state
+	pub vitals: Vitals,
+	pub size: (u32, u32),
+}
+
+impl Enemy{
+	pub fn new(kind: EnemyKind, enemy_types: &EnemyTypes, position: Vec2) -> Enemy{
+		let enemy_type = enemy_types.get(kind);
+		Enemy{
+			kind,
+			position,
+			velocity: Vec2::ZERO,
+			facing_vector: std::f32::consts::PI,
+			behaviour: enemy_type.initial,
+			state_ticks: 0,
+			vitals: Vitals::new(enemy_type.max_health, enemy_type.invulnerability),
+			size: enemy_type.size,
+		}
+	}
+
+	pub fn hurtbox(&self) -> Rect{
+		hurtbox(self.position.to_point(), self.size.0, self.size.1)
+	}
+
+	pub fn action(&self, enemy_types: &EnemyTypes) -> Action{
+		enemy_types.get(self.kind).states[self.behaviour].action
+	}
+
+	pub fn update(&mut self, player: &BattlePlayerContext, enemy_types: &EnemyTypes, arena: &Arena) -> Option<Hitbox>{
+		//moves the enemy one tick, returns its attack hitbox if it's swinging this tick
+		let enemy_type = enemy_types.get(self.kind);
+		if self.vitals.tick(&mut self.velocity, &enemy_type.movement) {
+			//getting knocked back interrupts whatever it was doing
+			self.enter_state(enemy_type.on_hit);
+			arena.move_and_slide(&mut self.position, &mut self.velocity, self.size);
+			return None;
+		}
+		self.take_transition(enemy_type, player);
+
+		let to_player = self.position.angle_to(player.position);
+		let distance = self.position.distance(player.position);
+		let mut hitbox = None;
+		//actions that move set where they want to go, the enemy speeds up or slows down toward that below
+		let mut target_velocity = Vec2::ZERO;
+		match enemy_type.states[self.behaviour].action {
+			Action::Idle => (),
+			Action::Approach{ speed } => {
+				self.facing_vector = to_player;
+				target_velocity = self.step(arena, to_player, speed);
+			},
+			Action::KeepDistance{ speed, distance: wanted } => {
+				self.facing_vector = to_player;
+				//a little slack either way so it doesn't jitter back and forth on the line
+				if distance > wanted + speed {
+					target_velocity = self.step(arena, to_player, speed);
+				}else if distance < wanted - speed {
+					target_velocity = self.step(arena, to_player + std::f32::consts::PI, speed);
+				}
+			},
+			Action::Telegraph => {
+				self.facing_vector = to_player;
+			},
+			Action::Attack{ reach, size, damage, knockback_ticks, hit_start, hit_end } => {
+				//keeps the facing it had when the swing started so the player can dodge it
+				if (hit_start..hit_end).contains(&self.state_ticks) {
+					hitbox = Some(Hitbox{
+						rect: attack_hitbox(self.position.to_point(), self.facing_vector, reach, size),
+						damage,
+						knockback_ticks,
+						status: None,
+					});
+				}
+			},
+			Action::Strafe{ speed, clockwise } => {
+				self.facing_vector = to_player;
+				let quarter_turn = std::f32::consts::FRAC_PI_2;
+				target_velocity = self.step(arena, to_player + if clockwise { -quarter_turn } else { quarter_turn }, speed);
+			},
+			Action::Flee{ speed } => {
+				self.facing_vector = to_player + std::f32::consts::PI;
+				target_velocity = self.step(arena, self.facing_vector, speed);
+			},
+		}
+		self.velocity = enemy_type.movement.steer(self.velocity, target_velocity);
+		arena.move_and_slide(&mut self.position, &mut self.velocity, self.size);
+		self.state_ticks += 1;
+		hitbox
+	}
+
+	fn take_transition(&mut self, enemy_type: &EnemyType, player: &BattlePlayerContext){
+		let transitions = &enemy_type.states[self.behaviour].transitions;
+		let next = transitions.iter().find(|transition| self.holds(&transition.condition, player));
+		if let Some(transition) = next {
+			self.enter_state(transition.to);
+		}
+	}
+
+	fn holds(&self, condition: &Condition, player: &BattlePlayerContext) -> bool{
+		match *condition {
+			Condition::Always => true,
+			Condition::After{ ticks } => self.state_ticks >= ticks,
+			Condition::PlayerWithin{ distance: within } => self.position.distance(player.position) <= within,
+			Condition::PlayerBeyond{ distance: beyond } => self.position.distance(player.position) > beyond,
+			Condition::HealthBelow{ fraction } => (self.vitals.health as f32) < self.vitals.max_health as f32 * fraction,
+			Condition::PlayerAttacking => matches!(player.state, PlayerState::Attacking(_,_)),
+			Condition::PlayerDead => player.vitals.is_dead(),
+		}
+	}
+
+	fn enter_state(&mut self, behaviour: usize){
+		self.behaviour = behaviour;
+		self.state_ticks = 0;
+	}
+
+	fn step(&self, arena: &Arena, angle: f32, speed: f32) -> Vec2{
+		//the velocity to walk along angle at, if something's mostly in the way it sidesteps around it instead of pushing against it forever
+		let speed = speed * self.vitals.speed_multiplier();
+		let quarter_turn = std::f32::consts::FRAC_PI_2;
+		for direction in [angle, angle + quarter_turn, angle - quarter_turn] {
+			let velocity = Vec2::from_angle(direction, speed);
+			let (mut position, mut moved) = (self.position, velocity);
+			arena.move_and_slide(&mut position, &mut moved, self.size);
+			if position.distance(self.position) > speed * 0.5 {
+				return velocity;
+			}
+		}
+		Vec2::ZERO
+	}
+}
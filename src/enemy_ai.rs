@@ -0,0 +1,149 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use serde::Deserialize;
+use crate::physics::MovementStats;
+
+// Enemy types and their behaviour, loaded from assets/data/enemies.toml.
+// Each type is a small state machine: every state runs one action (approach, keep distance, strafe, ...)
+// and lists transitions that are checked in order at the start of each tick, the first one whose
+// condition holds is taken. See the data file for the format.
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Action{
+	Idle,
+	Approach{ speed: f32 },
+	KeepDistance{ speed: f32, distance: f32 }, //backs off or closes in to stay around distance from the player
+	Telegraph, //stands still facing the player, drawn flashing so the player can see the attack coming
+	Attack{ reach: f32, size: u32, damage: i32, knockback_ticks: usize, hit_start: usize, hit_end: usize },
+	Strafe{ speed: f32, #[serde(default)] clockwise: bool },
+	Flee{ speed: f32 },
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(tag = "when", rename_all = "snake_case")]
+pub enum Condition{
+	Always,
+	After{ ticks: usize }, //ticks spent in the current state
+	PlayerWithin{ distance: f32 },
+	PlayerBeyond{ distance: f32 },
+	HealthBelow{ fraction: f32 },
+	PlayerAttacking,
+	PlayerDead,
+}
+
+#[derive(Deserialize)]
+struct TransitionDef{
+	to: String,
+	#[serde(flatten)]
+	condition: Condition,
+}
+
+#[derive(Deserialize)]
+struct StateDef{
+	#[serde(flatten)]
+	action: Action,
+	#[serde(default)]
+	transitions: Vec<TransitionDef>,
+}
+
+#[derive(Deserialize)]
+struct EnemyTypeDef{
+	max_health: i32,
+	invulnerability: usize,
+	size: [u32; 2],
+	initial: String,
+	on_hit: Option<String>, //state to switch to when knocked back, the initial state if missing
+	#[serde(default)]
+	movement: MovementStats, //instant acceleration if missing
+	states: BTreeMap<String, StateDef>,
+}
+
+#[derive(Deserialize)]
+struct EnemyTypesFile{
+	enemies: BTreeMap<String, EnemyTypeDef>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Transition{
+	pub to: usize,
+	pub condition: Condition,
+}
+
+#[derive(Clone, Debug)]
+pub struct BehaviourState{
+	pub action: Action,
+	pub transitions: Vec<Transition>,
+}
+
+#[derive(Clone, Debug)]
+pub struct EnemyType{
+	pub max_health: i32,
+	pub invulnerability: usize,
+	pub size: (u32, u32),
+	pub initial: usize,
+	pub on_hit: usize,
+	pub movement: MovementStats,
+	pub states: Vec<BehaviourState>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct EnemyKind(usize);
+
+#[derive(Debug)]
+pub struct EnemyTypes{
+	types: Vec<EnemyType>,
+	kinds: HashMap<String, EnemyKind>,
+}
+
+impl EnemyTypes{
+	pub fn load(path: &str) -> Result<EnemyTypes, String>{
+		let text = fs::read_to_string(path).map_err(|why| format!("Unable to read enemy types {}: {}", path, why))?;
+		let file: EnemyTypesFile = toml::from_str(&text).map_err(|why| format!("Unable to parse enemy types {}: {}", path, why))?;
+		let mut enemy_types = EnemyTypes{ types: Vec::new(), kinds: HashMap::new() };
+		for (name, def) in file.enemies {
+			let enemy_type = resolve_type(&name, def).map_err(|why| format!("{} in {}", why, path))?;
+			enemy_types.kinds.insert(name, EnemyKind(enemy_types.types.len()));
+			enemy_types.types.push(enemy_type);
+		}
+		Ok(enemy_types)
+	}
+
+	pub fn kind(&self, name: &str) -> Option<EnemyKind>{
+		self.kinds.get(name).copied()
+	}
+
+	pub fn get(&self, kind: EnemyKind) -> &EnemyType{
+		&self.types[kind.0]
+	}
+}
+
+fn resolve_type(name: &str, def: EnemyTypeDef) -> Result<EnemyType, String>{
+	//swaps state names for indices so the copyable enemy state only has to carry a number
+	let names: Vec<String> = def.states.keys().cloned().collect();
+	let index_of = |state: &str| names.iter().position(|name| name == state)
+		.ok_or(format!("Enemy {} has no state named {}", name, state));
+	let initial = index_of(&def.initial)?;
+	let on_hit = match &def.on_hit {
+		Some(state) => index_of(state)?,
+		None => initial,
+	};
+	let mut states = Vec::new();
+	for state_name in &names {
+		let state_def = &def.states[state_name];
+		let mut transitions = Vec::new();
+		for transition in &state_def.transitions {
+			transitions.push(Transition{ to: index_of(&transition.to)?, condition: transition.condition });
+		}
+		states.push(BehaviourState{ action: state_def.action, transitions });
+	}
+	Ok(EnemyType{
+		max_health: def.max_health,
+		invulnerability: def.invulnerability,
+		size: (def.size[0], def.size[1]),
+		initial,
+		on_hit,
+		movement: def.movement,
+		states,
+	})
+}
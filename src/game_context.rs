@@ -1,51 +1,48 @@
-use sdl2::render::{WindowCanvas, Texture};
+use sdl2::render::WindowCanvas;
+use crate::assets::AssetManager;
+use crate::game_data::GameData;
 use crate::game_context::GameContext::{Battle, StartScreen};
 use crate::screens::start::render_start_screen;
 use crate::screens::battle::{BattleContext, render_battle};
 use crate::screens::start::StartScreenContext;
-use crate::input::{InputState, get_player_intent_vector};
+use crate::input::InputState;
 use crate::sound_manager::SoundManager;
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Debug)]
 pub struct GameObject{
-    pub phase: GameContext,
-    pub player: Option<Player>,
+	pub phase: GameContext,
 }
 
 impl GameObject{
-    pub fn handle_tick(&mut self, input_state: &InputState, my_sound_manager: &mut SoundManager){
-        match self.phase.clone(){
-            Battle(_battle_context) =>{
-                BattleContext::handle_tick(self, input_state, my_sound_manager);
-            },
-            StartScreen(_start_context)=>{
-                StartScreenContext::handle_tick(self, input_state, my_sound_manager);
-            },
-            _ => {todo!("implement handle_tick for other game contexts")}
-        }
-    }
+	pub fn handle_tick(&mut self, input_state: &InputState, my_sound_manager: &mut SoundManager, data: &GameData){
+		match self.phase{
+			Battle(_) =>{
+				BattleContext::handle_tick(self, input_state, my_sound_manager, data);
+			},
+			StartScreen(_)=>{
+				StartScreenContext::handle_tick(self, input_state, my_sound_manager, data);
+			},
+		}
+	}
 
-    pub fn render(self, canvas: &mut WindowCanvas, background_texture: &Texture){
-        //will reach out to the draw functions of its phases
-        match self.phase {
-            Battle(battle)=> render_battle(canvas, background_texture, &battle),
-            StartScreen(ctx) => render_start_screen(canvas, background_texture, &ctx),
-            _ => todo!("implement render for other game phases")
-        }
-    }
+	pub fn render(&self, previous: &GameObject, alpha: f32, canvas: &mut WindowCanvas, assets: &mut AssetManager, data: &GameData){
+		//will reach out to the draw functions of its phases
+		//previous is the state one tick ago, alpha is how far we are from it towards self
+		match &self.phase {
+			Battle(battle)=> {
+				let previous_battle = match &previous.phase {
+					Battle(previous_battle) => Some(previous_battle.as_ref()),
+					_ => None
+				};
+				render_battle(canvas, assets, data, battle, previous_battle, alpha)
+			},
+			StartScreen(ctx) => render_start_screen(canvas, assets, ctx),
+		}
+	}
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Debug)]
 pub enum GameContext{
-    StartScreen(StartScreenContext),
-    Walking,
-    PartyMenu,
-    ForcedAction,
-    Conversation,
-    Battle(BattleContext),
+	StartScreen(StartScreenContext),
+	Battle(Box<BattleContext>), //boxed, a battle is far bigger than any other phase
 }
-
-#[derive(Clone, Copy)]
-pub struct Player{
-    //player stat things go here
-}
\ No newline at end of file
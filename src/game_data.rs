@@ -0,0 +1,77 @@
+use crate::animation::SpriteSheet;
+use crate::arena::{ArenaId, Arenas};
+use crate::attacks::Attacks;
+use crate::enemy_ai::EnemyTypes;
+use crate::items::Items;
+use crate::player_stats::PlayerStats;
+use crate::spells::{SpellId, Spells};
+
+// Gameplay data loaded from assets/data at startup. Unlike the asset manager this doesn't need a window,
+// so the headless runs load it too and the simulation plays out the same with or without a display.
+
+pub const PLAYER_ANIMATIONS: &str = "assets/data/player_animations.toml";
+pub const ENEMY_TYPES: &str = "assets/data/enemies.toml";
+pub const PLAYER_STATS: &str = "assets/data/player.toml";
+pub const SPELLS: &str = "assets/data/spells.toml";
+pub const ATTACKS: &str = "assets/data/attacks.toml";
+pub const ITEMS: &str = "assets/data/items.toml";
+pub const ARENAS: &str = "assets/data/arenas";
+pub const DEFAULT_ARENA: &str = "rooftop";
+
+#[derive(Debug)]
+pub struct GameData{
+	pub player_sprites: SpriteSheet,
+	pub enemy_types: EnemyTypes,
+	pub player_stats: PlayerStats,
+	pub spells: Spells,
+	pub player_spell: SpellId, //the spell named in the player stats
+	pub known_spells: Vec<SpellId>, //the spells the player can equip, in the order the player stats list them
+	pub attacks: Attacks,
+	pub items: Items,
+	pub arenas: Arenas,
+	pub default_arena: ArenaId,
+}
+
+impl GameData{
+	pub fn load() -> Result<GameData, String>{
+		let player_stats = PlayerStats::load(PLAYER_STATS)?;
+		let spells = Spells::load(SPELLS)?;
+		let player_spell = spells.id(&player_stats.casting.spell)
+			.ok_or(format!("No spell named {} in {}", player_stats.casting.spell, SPELLS))?;
+		let known_spells = player_stats.casting.known_spells.iter()
+			.map(|name| spells.id(name).ok_or(format!("No spell named {} in {}", name, SPELLS)))
+			.collect::<Result<Vec<_>, _>>()?;
+		if !known_spells.contains(&player_spell) {
+			return Err(format!("The player's spell {} isn't one of their known_spells in {}", player_stats.casting.spell, PLAYER_STATS));
+		}
+		let arenas = Arenas::load_dir(ARENAS)?;
+		let default_arena = arenas.id(DEFAULT_ARENA)
+			.ok_or(format!("No arena named {} in {}", DEFAULT_ARENA, ARENAS))?;
+		let enemy_types = EnemyTypes::load(ENEMY_TYPES)?;
+		for (name, arena) in arenas.named() {
+			if let Some(spawn) = arena.spawns.iter().find(|spawn| enemy_types.kind(&spawn.enemy).is_none()) {
+				return Err(format!("Arena {} spawns {}, which isn't an enemy in {}", name, spawn.enemy, ENEMY_TYPES));
+			}
+		}
+		//spells come out on the casting animation's release event, which plays a tick behind the cast, so a cast has to outlast it
+		let player_sprites = SpriteSheet::load(PLAYER_ANIMATIONS)?;
+		let release_tick = player_sprites.clip_id("casting")
+			.and_then(|clip| player_sprites.clip(clip).event_tick("release"))
+			.ok_or(format!("The casting clip in {} has no release event", PLAYER_ANIMATIONS))?;
+		if let Some((name, spell)) = spells.named().find(|(_, spell)| spell.cast_ticks <= release_tick) {
+			return Err(format!("Spell {} casts for {} ticks, but the casting clip in {} only releases it after tick {}", name, spell.cast_ticks, PLAYER_ANIMATIONS, release_tick));
+		}
+		Ok(GameData{
+			player_sprites,
+			enemy_types,
+			player_stats,
+			spells,
+			player_spell,
+			known_spells,
+			attacks: Attacks::load(ATTACKS)?,
+			items: Items::load(ITEMS)?,
+			arenas,
+			default_arena,
+		})
+	}
+}
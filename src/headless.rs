@@ -0,0 +1,212 @@
+use std::fs;
+use crate::game_context::{GameContext, GameObject};
+use crate::game_data::GameData;
+use crate::game_context::GameContext::Battle;
+use crate::input::InputState;
+use crate::replay::InputReplay;
+use crate::sound_manager::SoundManager;
+
+// Runs the simulation without a window or audio device so it can be driven from CI.
+// Scripts are plain text, one step per line:
+//   30 btn_start            hold btn_start for 30 ticks
+//   10 left_x_pos=32767     hold the left stick fully right for 10 ticks
+//   5                       5 ticks with nothing held
+//   expect Battle           fail unless the game is currently in that phase
+//   expect player_x>400     fail unless a battle is running and the value compares that way, with =, < or >
+// Values that can be checked are player_health, player_mana, player_stamina, player_x, player_y and enemies (how many are left).
+// Anything after a # is a comment.
+
+pub enum ScriptStep{
+	Hold(usize, InputState),
+	Expect(String),
+	Check(String, Comparison, f32),
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Comparison{
+	Equal,
+	Less,
+	Greater,
+}
+
+impl Comparison{
+	fn holds(&self, actual: f32, expected: f32) -> bool{
+		match self {
+			//close enough for positions that have been through float steps
+			Comparison::Equal => (actual - expected).abs() < 0.01,
+			Comparison::Less => actual < expected,
+			Comparison::Greater => actual > expected,
+		}
+	}
+
+	fn symbol(&self) -> char{
+		match self {
+			Comparison::Equal => '=',
+			Comparison::Less => '<',
+			Comparison::Greater => '>',
+		}
+	}
+}
+
+pub fn parse_script(text: &str) -> Result<Vec<ScriptStep>, String>{
+	let mut steps = Vec::new();
+	for (line_num, line) in text.lines().enumerate() {
+		let line = line.split('#').next().unwrap_or("").trim();
+		if line.is_empty() {
+			continue;
+		}
+		let mut words = line.split_whitespace();
+		let first = words.next().unwrap();
+		if first == "expect" {
+			match words.next() {
+				Some(check) if check.contains(['=', '<', '>']) => {
+					steps.push(parse_check(check).map_err(|why| format!("line {}: {}", line_num + 1, why))?);
+				},
+				Some(phase) => steps.push(ScriptStep::Expect(phase.to_string())),
+				None => return Err(format!("line {}: expect needs a phase name or a check", line_num + 1)),
+			}
+			continue;
+		}
+		let ticks: usize = first.parse()
+			.map_err(|_| format!("line {}: expected a tick count, got {}", line_num + 1, first))?;
+		let mut input = InputState::new();
+		for word in words {
+			set_input_field(&mut input, word).map_err(|why| format!("line {}: {}", line_num + 1, why))?;
+		}
+		steps.push(ScriptStep::Hold(ticks, input));
+	}
+	Ok(steps)
+}
+
+fn parse_check(check: &str) -> Result<ScriptStep, String>{
+	let split = check.find(['=', '<', '>']).unwrap();
+	let comparison = match &check[split..split + 1] {
+		"=" => Comparison::Equal,
+		"<" => Comparison::Less,
+		_ => Comparison::Greater,
+	};
+	let (name, value) = (&check[..split], &check[split + 1..]);
+	let value = value.parse().map_err(|_| format!("{} needs a number to compare with, got {}", name, value))?;
+	Ok(ScriptStep::Check(name.to_string(), comparison, value))
+}
+
+fn set_input_field(input: &mut InputState, word: &str) -> Result<(), String>{
+	let (name, value) = match word.split_once('=') {
+		Some((name, value)) => (name, Some(value)),
+		None => (word, None),
+	};
+	let flag = match value {
+		None => true,
+		Some(value) => value == "true" || value == "1",
+	};
+	let axis = || -> Result<i16, String> {
+		value.ok_or(format!("{} needs a value", name))?
+			.parse()
+			.map_err(|_| format!("{} needs a value between -32768 and 32767", name))
+	};
+	match name {
+		"left_x_pos" => input.left_x_pos = axis()?,
+		"left_y_pos" => input.left_y_pos = axis()?,
+		"right_x_pos" => input.right_x_pos = axis()?,
+		"right_y_pos" => input.right_y_pos = axis()?,
+		"trig_left_pos" => input.trig_left_pos = axis()?,
+		"trig_right_pos" => input.trig_right_pos = axis()?,
+		"left_shoulder" => input.left_shoulder = flag,
+		"right_shoulder" => input.right_shoulder = flag,
+		"dpad_up" => input.dpad_up = flag,
+		"dpad_down" => input.dpad_down = flag,
+		"dpad_left" => input.dpad_left = flag,
+		"dpad_right" => input.dpad_right = flag,
+		"btn_left" => input.btn_left = flag,
+		"btn_right" => input.btn_right = flag,
+		"btn_up" => input.btn_up = flag,
+		"btn_down" => input.btn_down = flag,
+		"btn_start" => input.btn_start = flag,
+		"btn_back" => input.btn_back = flag,
+		"left_stick" => input.left_stick = flag,
+		"right_stick" => input.right_stick = flag,
+		"left_trigger" => input.left_trigger = flag,
+		"right_trigger" => input.right_trigger = flag,
+		"controller_disconnected" => input.controller_disconnected = flag,
+		_ => return Err(format!("unknown input {}", name)),
+	};
+	Ok(())
+}
+
+pub fn phase_name(phase: &GameContext) -> &'static str{
+	match phase {
+		GameContext::StartScreen(_) => "StartScreen",
+		GameContext::Battle(_) => "Battle",
+	}
+}
+
+fn checked_value(phase: &GameContext, name: &str) -> Result<f32, String>{
+	let ctx = match phase {
+		Battle(ctx) => ctx,
+		phase => return Err(format!("{} can only be checked in a battle, not in {}", name, phase_name(phase))),
+	};
+	match name {
+		"player_health" => Ok(ctx.player.vitals.health as f32),
+		"player_mana" => Ok(ctx.player.mana),
+		"player_stamina" => Ok(ctx.player.stamina),
+		"player_x" => Ok(ctx.player.position.x),
+		"player_y" => Ok(ctx.player.position.y),
+		"enemies" => Ok(ctx.enemies.iter().flatten().count() as f32),
+		_ => Err(format!("unknown value {}", name)),
+	}
+}
+
+pub fn run_script(game_obj: &mut GameObject, data: &GameData, steps: &[ScriptStep]) -> Result<usize, String>{
+	//returns how many ticks were simulated
+	let mut sound_manager = SoundManager::headless();
+	sound_manager.preload_sound_effects()?;
+	let mut previous_input = InputState::new();
+	let mut tick = 0;
+	for step in steps {
+		match step {
+			ScriptStep::Hold(ticks, input) => {
+				for _ in 0..*ticks {
+					let mut tick_input = *input;
+					tick_input.advance_tick(&previous_input);
+					game_obj.handle_tick(&tick_input, &mut sound_manager, data);
+					previous_input = tick_input;
+					tick += 1;
+				}
+			},
+			ScriptStep::Expect(expected) => {
+				let actual = phase_name(&game_obj.phase);
+				if actual != expected {
+					return Err(format!("tick {}: expected phase {} but was in {}", tick, expected, actual));
+				}
+			},
+			ScriptStep::Check(name, comparison, expected) => {
+				let actual = checked_value(&game_obj.phase, name).map_err(|why| format!("tick {}: {}", tick, why))?;
+				if !comparison.holds(actual, *expected) {
+					return Err(format!("tick {}: expected {}{}{} but it was {}", tick, name, comparison.symbol(), expected, actual));
+				}
+			}
+		}
+	}
+	Ok(tick)
+}
+
+pub fn run_script_file(game_obj: &mut GameObject, data: &GameData, path: &str) -> Result<usize, String>{
+	let text = fs::read_to_string(path).map_err(|why| format!("Unable to read script {}: {}", path, why))?;
+	let steps = parse_script(&text)?;
+	run_script(game_obj, data, &steps)
+}
+
+pub fn run_replay(game_obj: &mut GameObject, data: &GameData, replay: &mut InputReplay) -> Result<usize, String>{
+	//feeds every recorded tick through the simulation, returns how many ticks were simulated
+	let mut sound_manager = SoundManager::headless();
+	sound_manager.preload_sound_effects()?;
+	let mut previous_input = InputState::new();
+	let mut tick = 0;
+	while let Some(mut input) = replay.next_tick() {
+		input.advance_tick(&previous_input);
+		game_obj.handle_tick(&input, &mut sound_manager, data);
+		previous_input = input;
+		tick += 1;
+	}
+	Ok(tick)
+}
@@ -1,8 +1,9 @@
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
-use sdl2::controller::{Axis, Button};
+use serde::{Deserialize, Serialize};
+use crate::bindings::Bindings;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum ControllerType{
 	Keyboard,
 	Gamepad
@@ -10,21 +11,113 @@ pub enum ControllerType{
 
 pub struct ControllerSettings{
 	pub dead_zone: i16,
+	pub stick_response: StickResponse, //how the sticks are shaped past the dead zone
 	pub left_trigger_activation_threshold: i16,
 	pub right_trigger_activation_threshold: i16,
+	pub bindings: Bindings,
 }
 
 impl ControllerSettings{
 	pub fn new() -> ControllerSettings{
 		ControllerSettings{
 			dead_zone: 2000,
+			stick_response: StickResponse::default(),
 			left_trigger_activation_threshold: 10000,
 			right_trigger_activation_threshold: 10000,
+			bindings: Bindings::new(),
 		}
 	}
 }
 
-#[derive(Debug)]
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum DeadZoneShape{
+	Radial, //the dead zone is a circle around the center, the stick's direction isn't bent near the axes
+	Axial, //each axis has its own dead zone, easier to hold a straight line
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum ResponseCurve{
+	Linear,
+	Quadratic, //finer control over small tilts, the same at full tilt
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(default)]
+pub struct StickResponse{
+	pub dead_zone_shape: DeadZoneShape,
+	pub response_curve: ResponseCurve,
+}
+
+impl Default for StickResponse{
+	fn default() -> StickResponse{
+		StickResponse{
+			dead_zone_shape: DeadZoneShape::Radial,
+			response_curve: ResponseCurve::Linear,
+		}
+	}
+}
+
+// Where the player is trying to go, from the left stick or the dpad
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Intent{
+	pub direction: f32, //angle, counterclockwise with right being 0
+	pub magnitude: f32, //how far the stick is pushed from 0 to 1, always 1 for the dpad and keys
+}
+
+// The logical buttons InputState tracks, used to ask about presses and releases
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum InputButton{
+	LeftShoulder,
+	RightShoulder,
+	DpadUp,
+	DpadDown,
+	DpadLeft,
+	DpadRight,
+	Left,
+	Right,
+	Up,
+	Down,
+	Start,
+	Back,
+	LeftStick,
+	RightStick,
+	LeftTrigger, //the triggers count as held past their activation threshold
+	RightTrigger,
+}
+pub const BUTTON_COUNT: usize = 16;
+impl InputButton{
+	pub const ALL: [InputButton; BUTTON_COUNT] = [
+		InputButton::LeftShoulder,
+		InputButton::RightShoulder,
+		InputButton::DpadUp,
+		InputButton::DpadDown,
+		InputButton::DpadLeft,
+		InputButton::DpadRight,
+		InputButton::Left,
+		InputButton::Right,
+		InputButton::Up,
+		InputButton::Down,
+		InputButton::Start,
+		InputButton::Back,
+		InputButton::LeftStick,
+		InputButton::RightStick,
+		InputButton::LeftTrigger,
+		InputButton::RightTrigger,
+	];
+}
+
+// The logical analog inputs InputState tracks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum InputAxis{
+	LeftX,
+	LeftY,
+	RightX,
+	RightY,
+	TriggerLeft,
+	TriggerRight,
+}
+
+#[derive(Debug, Clone, Copy)]
 pub struct InputState{
 	pub device: ControllerType,
 	pub left_x_pos: i16,
@@ -47,7 +140,18 @@ pub struct InputState{
 	pub btn_back: bool,
 	pub left_stick: bool,
 	pub right_stick: bool,
+	pub left_trigger: bool,
+	pub right_trigger: bool,
 	pub shutdown: bool,
+	pub controller_disconnected: bool, //set for one tick when the pad in use is unplugged
+	//how many ticks each button has been held for, indexed like InputButton::ALL
+	//filled in by advance_tick, 0 means not held
+	held_ticks: [u32; BUTTON_COUNT],
+	previous_held_ticks: [u32; BUTTON_COUNT], //held_ticks as the last tick saw them, for releases
+	//how many bound keys/buttons are holding each button down, so releasing one of two bindings doesn't release the button
+	held_sources: [u8; BUTTON_COUNT],
+	//the sticks as the pad reports them, indexed like InputAxis, the pos fields above are these after the dead zone and response curve
+	raw_sticks: [i16; 4],
 }
 impl InputState{
 	pub fn new() -> InputState{
@@ -73,18 +177,185 @@ impl InputState{
 			btn_back: false,
 			left_stick: false,
 			right_stick: false,
-			shutdown: false
+			left_trigger: false,
+			right_trigger: false,
+			shutdown: false,
+			controller_disconnected: false,
+			held_ticks: [0; BUTTON_COUNT],
+			previous_held_ticks: [0; BUTTON_COUNT],
+			held_sources: [0; BUTTON_COUNT],
+			raw_sticks: [0; 4],
+		}
+	}
+
+	pub fn is_held(&self, button: InputButton) -> bool{
+		match button {
+			InputButton::LeftShoulder => self.left_shoulder,
+			InputButton::RightShoulder => self.right_shoulder,
+			InputButton::DpadUp => self.dpad_up,
+			InputButton::DpadDown => self.dpad_down,
+			InputButton::DpadLeft => self.dpad_left,
+			InputButton::DpadRight => self.dpad_right,
+			InputButton::Left => self.btn_left,
+			InputButton::Right => self.btn_right,
+			InputButton::Up => self.btn_up,
+			InputButton::Down => self.btn_down,
+			InputButton::Start => self.btn_start,
+			InputButton::Back => self.btn_back,
+			InputButton::LeftStick => self.left_stick,
+			InputButton::RightStick => self.right_stick,
+			InputButton::LeftTrigger => self.left_trigger,
+			InputButton::RightTrigger => self.right_trigger,
+		}
+	}
+
+	fn set_held(&mut self, button: InputButton, held: bool){
+		match button {
+			InputButton::LeftShoulder => self.left_shoulder = held,
+			InputButton::RightShoulder => self.right_shoulder = held,
+			InputButton::DpadUp => self.dpad_up = held,
+			InputButton::DpadDown => self.dpad_down = held,
+			InputButton::DpadLeft => self.dpad_left = held,
+			InputButton::DpadRight => self.dpad_right = held,
+			InputButton::Left => self.btn_left = held,
+			InputButton::Right => self.btn_right = held,
+			InputButton::Up => self.btn_up = held,
+			InputButton::Down => self.btn_down = held,
+			InputButton::Start => self.btn_start = held,
+			InputButton::Back => self.btn_back = held,
+			InputButton::LeftStick => self.left_stick = held,
+			InputButton::RightStick => self.right_stick = held,
+			InputButton::LeftTrigger => self.left_trigger = held,
+			InputButton::RightTrigger => self.right_trigger = held,
+		}
+	}
+
+	fn press_source(&mut self, button: InputButton, pressed: bool){
+		let sources = &mut self.held_sources[button as usize];
+		*sources = if pressed { sources.saturating_add(1) } else { sources.saturating_sub(1) };
+		let held = *sources > 0;
+		self.set_held(button, held);
+	}
+
+	pub fn release_all(&mut self){
+		//lets go of every button and recenters every axis, held durations are kept so the next tick sees the releases
+		for button in InputButton::ALL {
+			self.set_held(button, false);
+		}
+		self.held_sources = [0; BUTTON_COUNT];
+		self.left_x_pos = 0;
+		self.left_y_pos = 0;
+		self.right_x_pos = 0;
+		self.right_y_pos = 0;
+		self.trig_left_pos = 0;
+		self.trig_right_pos = 0;
+		//and forgets the raw sticks too, or the next pad's first axis would be shaped against the old pad's other one
+		self.raw_sticks = [0; 4];
+	}
+
+	fn set_axis(&mut self, axis: InputAxis, value: i16){
+		match axis {
+			InputAxis::LeftX => self.left_x_pos = value,
+			InputAxis::LeftY => self.left_y_pos = value,
+			InputAxis::RightX => self.right_x_pos = value,
+			InputAxis::RightY => self.right_y_pos = value,
+			InputAxis::TriggerLeft => self.trig_left_pos = value,
+			InputAxis::TriggerRight => self.trig_right_pos = value,
+		}
+	}
+
+	fn set_trigger_axis(&mut self, axis: InputAxis, value: i16, controller_settings: &ControllerSettings){
+		//sets the axis, and for the triggers presses or releases their button when it crosses the activation threshold
+		let trigger = match axis {
+			InputAxis::TriggerLeft => Some((InputButton::LeftTrigger, self.trig_left_pos, controller_settings.left_trigger_activation_threshold)),
+			InputAxis::TriggerRight => Some((InputButton::RightTrigger, self.trig_right_pos, controller_settings.right_trigger_activation_threshold)),
+			_ => None
+		};
+		self.set_axis(axis, value);
+		if let Some((button, previous_value, threshold)) = trigger {
+			let was_pressed = previous_value > threshold;
+			let pressed = value > threshold;
+			if pressed != was_pressed {
+				self.press_source(button, pressed);
+			}
 		}
 	}
+
+	fn set_stick_axis(&mut self, axis: InputAxis, value: i16, controller_settings: &ControllerSettings){
+		//a stick's dead zone and curve depend on both of its axes, so moving one reshapes the pair
+		let (x_axis, y_axis) = match axis {
+			InputAxis::LeftX | InputAxis::LeftY => (InputAxis::LeftX, InputAxis::LeftY),
+			_ => (InputAxis::RightX, InputAxis::RightY),
+		};
+		self.raw_sticks[axis as usize] = value;
+		let (x, y) = shape_stick(self.raw_sticks[x_axis as usize], self.raw_sticks[y_axis as usize], controller_settings.dead_zone, &controller_settings.stick_response);
+		self.set_axis(x_axis, x);
+		self.set_axis(y_axis, y);
+	}
+
+	pub fn advance_tick(&mut self, previous: &InputState){
+		//call once per tick before handing the state to the simulation
+		//previous is the state the last tick saw, the counters are carried over from it
+		for (i, button) in InputButton::ALL.iter().enumerate() {
+			self.previous_held_ticks[i] = previous.held_ticks[i];
+			self.held_ticks[i] = if self.is_held(*button) { previous.held_ticks[i] + 1 } else { 0 };
+		}
+	}
+
+	pub fn held_ticks(&self, button: InputButton) -> u32{
+		self.held_ticks[button as usize]
+	}
+
+	pub fn just_pressed(&self, button: InputButton) -> bool{
+		self.held_ticks[button as usize] == 1
+	}
+
+	pub fn just_released(&self, button: InputButton) -> bool{
+		self.held_ticks[button as usize] == 0 && self.previous_held_ticks[button as usize] > 0
+	}
 }
 
-pub fn get_player_intent_vector(input: &InputState) -> Option<f32>{
+fn shape_stick(x: i16, y: i16, dead_zone: i16, response: &StickResponse) -> (i16, i16){
+	//cuts out the dead zone and stretches what's left back over the full range, so just past the dead zone is 0
+	//and full tilt is still full tilt, then bends the magnitude by the response curve
+	let dead_zone = (dead_zone.max(0) as f32 / i16::MAX as f32).min(0.99);
+	let rescale = |amount: f32| ((amount - dead_zone) / (1.0 - dead_zone)).clamp(0.0, 1.0);
+	let (x, y) = (x as f32 / i16::MAX as f32, y as f32 / i16::MAX as f32);
+	let (x, y) = match response.dead_zone_shape {
+		DeadZoneShape::Radial => {
+			let length = x.hypot(y);
+			if length <= dead_zone {
+				(0.0, 0.0)
+			}else{
+				let scale = rescale(length) / length;
+				(x * scale, y * scale)
+			}
+		},
+		DeadZoneShape::Axial => (rescale(x.abs()).copysign(x), rescale(y.abs()).copysign(y)),
+	};
+	let length = x.hypot(y);
+	if length == 0.0 {
+		return (0, 0);
+	}
+	let magnitude = match response.response_curve {
+		ResponseCurve::Linear => length.min(1.0),
+		ResponseCurve::Quadratic => length.min(1.0).powi(2),
+	};
+	let scale = magnitude / length * i16::MAX as f32;
+	((x * scale).round() as i16, (y * scale).round() as i16)
+}
+
+pub fn get_player_intent_vector(input: &InputState) -> Option<Intent>{
+	//the stick axes are already shaped by shape_stick, so any tilt left here is deliberate
 	let pi = std::f32::consts::PI;
 	if input.left_y_pos != 0 || input.left_x_pos != 0 {
-		let theta = (-1.0 * input.left_y_pos as f32).atan2(input.left_x_pos as f32);
-		return Some(theta);
+		let (x, y) = (input.left_x_pos as f32, input.left_y_pos as f32);
+		return Some(Intent{
+			direction: (-y).atan2(x),
+			magnitude: (x.hypot(y) / i16::MAX as f32).min(1.0),
+		});
 	}
-	match (&input.dpad_left, &input.dpad_right, &input.dpad_up, &input.dpad_down){
+	let direction = match (&input.dpad_left, &input.dpad_right, &input.dpad_up, &input.dpad_down){
 		(false, false, false, false) =>  None,
 		(false, true, false, false)  =>  Some(0.0),
 		(false, true, true, false)   =>  Some(0.25*pi),
@@ -95,107 +366,122 @@ pub fn get_player_intent_vector(input: &InputState) -> Option<f32>{
 		(false, false, false, true)  =>  Some(1.5*pi),
 		(false, true, false, true)   =>  Some(1.75*pi),
 		_                            =>  None
-	}
+	};
+	direction.map(|direction| Intent{ direction, magnitude: 1.0 })
 }
 
 pub fn read_input_event(input: &mut InputState, controller_settings: &ControllerSettings, event: &Event){
+	let bindings = &controller_settings.bindings;
 	match event {
 		Event::KeyDown {keycode: Some(Keycode::Escape), .. } | Event::Quit { .. } => {input.shutdown = true;},
+		Event::KeyDown {repeat: true, ..} => (),
 		Event::KeyDown {keycode: Some(code),..} => {
 			input.device = ControllerType::Keyboard;
-			match code{
-				Keycode::W => { input.dpad_up = true },
-				Keycode::A => { input.dpad_left = true },
-				Keycode::D => { input.dpad_right = true },
-				Keycode::S => { input.dpad_down = true },
-				Keycode::I => { input.btn_up = true },
-				Keycode::J => { input.btn_left = true },
-				Keycode::K => { input.btn_down = true },
-				Keycode::L => { input.btn_right = true },
-				Keycode::E => { input.left_shoulder = true },
-				Keycode::U => { input.right_shoulder = true },
-				Keycode::Return => { input.btn_start = true },
-				_ => ()
+			for button in bindings.buttons_for_key(*code) {
+				input.press_source(*button, true);
 			}
 		},
 		Event::KeyUp {keycode: Some(code),..} => {
 			input.device = ControllerType::Keyboard;
-			match code{
-				Keycode::W => { input.dpad_up = false },
-				Keycode::A => { input.dpad_left = false },
-				Keycode::D => { input.dpad_right = false },
-				Keycode::S => { input.dpad_down = false },
-				Keycode::I => { input.btn_up = false },
-				Keycode::J => { input.btn_left = false },
-				Keycode::K => { input.btn_down = false },
-				Keycode::L => { input.btn_right = false },
-				Keycode::E => { input.left_shoulder = false },
-				Keycode::U => { input.right_shoulder = false },
-				Keycode::Return => { input.btn_start = false },
-				_ => ()
-			};
+			for button in bindings.buttons_for_key(*code) {
+				input.press_source(*button, false);
+			}
 		},
 		Event::ControllerAxisMotion { axis, value: val, .. } => {
 			input.device = ControllerType::Gamepad;
 			let dead_zone = controller_settings.dead_zone;
-			match (axis, val) {
-				(Axis::LeftX, val) if val < &dead_zone && val > &-dead_zone => { input.left_x_pos = 0;},
-				(Axis::LeftX, val) => {input.left_x_pos = *val;},
-				(Axis::LeftY, val) if val < &dead_zone && val > &-dead_zone => { input.left_y_pos = 0;},
-				(Axis::LeftY, val) => {input.left_y_pos = *val;},
-				(Axis::RightX, val) if val < &dead_zone && val > &-dead_zone => { input.right_x_pos = 0;},
-				(Axis::RightX, val) => {input.right_x_pos = *val;},
-				(Axis::RightY, val) if val < &dead_zone && val > &-dead_zone => { input.right_y_pos = 0;},
-				(Axis::RightY, val) => {input.right_y_pos = *val;},
-				(Axis::TriggerLeft, val) if val < &dead_zone && val > &-dead_zone => { input.trig_left_pos = 0;},
-				(Axis::TriggerLeft, val) => {input.trig_left_pos = *val;},
-				(Axis::TriggerRight, val) if val < &dead_zone && val > &-dead_zone => { input.trig_right_pos = 0;},
-				(Axis::TriggerRight, val) => {input.trig_right_pos = *val;},
+			for logical_axis in bindings.axes_for_gamepad_axis(*axis) {
+				match logical_axis {
+					InputAxis::TriggerLeft | InputAxis::TriggerRight => {
+						let value = if *val < dead_zone && *val > -dead_zone { 0 } else { *val };
+						input.set_trigger_axis(*logical_axis, value, controller_settings);
+					},
+					_ => input.set_stick_axis(*logical_axis, *val, controller_settings),
+				}
 			}
 		}
 		Event::ControllerButtonDown { button, .. } => {
 			input.device = ControllerType::Gamepad;
-			match button {
-				Button::A =>             { input.btn_down = true },
-				Button::X =>             { input.btn_left = true },
-				Button::Y =>             { input.btn_up = true },
-				Button::B =>             { input.btn_right = true },
-				Button::LeftShoulder =>  { input.left_shoulder = true },
-				Button::RightShoulder => { input.right_shoulder = true },
-				Button::DPadDown =>      { input.dpad_down = true },
-				Button::DPadLeft =>      { input.dpad_left = true },
-				Button::DPadRight =>     { input.dpad_right = true },
-				Button::DPadUp =>        { input.dpad_up = true },
-				Button::Start =>         { input.btn_start = true },
-				Button::Back =>          { input.btn_back = true },
-				Button::LeftStick =>     { input.left_stick = true },
-				Button::RightStick =>    { input.right_stick = true },
-				_ => ()
+			for logical_button in bindings.buttons_for_gamepad_button(*button) {
+				input.press_source(*logical_button, true);
 			}
 		},
 		Event::ControllerButtonUp { button, .. } => {
 			input.device = ControllerType::Gamepad;
-			match button{
-				Button::A =>             { input.btn_down = false },
-				Button::X =>             { input.btn_left = false },
-				Button::Y =>             { input.btn_up = false },
-				Button::B =>             { input.btn_right = false },
-				Button::LeftShoulder =>  { input.left_shoulder = false },
-				Button::RightShoulder => { input.right_shoulder = false },
-				Button::DPadDown =>      { input.dpad_down = false },
-				Button::DPadLeft =>      { input.dpad_left = false },
-				Button::DPadRight =>     { input.dpad_right = false },
-				Button::DPadUp =>        { input.dpad_up = false },
-				Button::Start =>         { input.btn_start = false },
-				Button::Back =>          { input.btn_back = false },
-				Button::LeftStick =>     { input.left_stick = false },
-				Button::RightStick =>    { input.right_stick = false },
-				_ => ()
+			for logical_button in bindings.buttons_for_gamepad_button(*button) {
+				input.press_source(*logical_button, false);
 			}
 		},
-		Event::ControllerDeviceAdded { .. } => { println!("Controller added"); },
-		Event::ControllerDeviceRemoved { .. } => { println!("Controller removed"); },
 		_ => {}
 	}
 }
 
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const RADIAL: StickResponse = StickResponse{ dead_zone_shape: DeadZoneShape::Radial, response_curve: ResponseCurve::Linear };
+	const AXIAL: StickResponse = StickResponse{ dead_zone_shape: DeadZoneShape::Axial, response_curve: ResponseCurve::Linear };
+
+	#[test]
+	fn presses_count_up_while_held_and_release_once(){
+		let mut previous = InputState::new();
+		let mut ticks = Vec::new();
+		for held in [true, true, true, false, false] {
+			let mut input = previous;
+			input.btn_start = held;
+			input.advance_tick(&previous);
+			ticks.push((input.just_pressed(InputButton::Start), input.held_ticks(InputButton::Start), input.just_released(InputButton::Start)));
+			previous = input;
+		}
+		assert_eq!(ticks, vec![(true, 1, false), (false, 2, false), (false, 3, false), (false, 0, true), (false, 0, false)]);
+	}
+
+	#[test]
+	fn releasing_everything_recenters_the_raw_sticks(){
+		let mut input = InputState::new();
+		input.raw_sticks = [20000, -20000, 0, 0];
+		input.left_x_pos = 20000;
+		input.release_all();
+		assert_eq!(input.raw_sticks, [0; 4]);
+		assert_eq!(input.left_x_pos, 0);
+	}
+
+	#[test]
+	fn dead_zone_reads_as_centered(){
+		assert_eq!(shape_stick(2000, -2000, 3277, &RADIAL), (0, 0));
+		assert_eq!(shape_stick(3000, 0, 3277, &AXIAL), (0, 0));
+	}
+
+	#[test]
+	fn full_tilt_stays_full_tilt(){
+		assert_eq!(shape_stick(i16::MAX, 0, 3277, &RADIAL), (i16::MAX, 0));
+		assert_eq!(shape_stick(0, i16::MIN, 3277, &AXIAL), (0, -i16::MAX));
+	}
+
+	#[test]
+	fn rest_of_the_range_is_stretched_past_the_dead_zone(){
+		//a tenth of the range is dead, so 55% tilt is halfway through what's left
+		let (x, y) = shape_stick((i16::MAX as f32 * 0.55) as i16, 0, i16::MAX / 10, &RADIAL);
+		assert!((x - i16::MAX / 2).abs() < 20, "x was {}", x);
+		assert_eq!(y, 0);
+	}
+
+	#[test]
+	fn axial_drops_a_small_cross_axis(){
+		let (x, y) = shape_stick(20000, 2500, 3277, &AXIAL);
+		assert!(x > 0);
+		assert_eq!(y, 0);
+		//radial keeps the direction instead
+		let (_, y) = shape_stick(20000, 2500, 3277, &RADIAL);
+		assert!(y > 0);
+	}
+
+	#[test]
+	fn quadratic_softens_small_tilts(){
+		let quadratic = StickResponse{ dead_zone_shape: DeadZoneShape::Radial, response_curve: ResponseCurve::Quadratic };
+		let (x, _) = shape_stick(i16::MAX / 2, 0, 0, &quadratic);
+		assert!((x - i16::MAX / 4).abs() < 20, "x was {}", x);
+		assert_eq!(shape_stick(i16::MAX, 0, 0, &quadratic), (i16::MAX, 0));
+	}
+}
@@ -0,0 +1,88 @@
+use std::collections::BTreeMap;
+use std::fs;
+use serde::Deserialize;
+
+// Items the player can use from the pause menu, loaded from assets/data/items.toml.
+// Every battle starts the player with each item's count, what's left is kept in the copyable battle state.
+
+pub const MAX_ITEMS: usize = 16;
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Item{
+	pub name: String, //shown in the menu
+	#[serde(default)]
+	pub description: String, //shown under the list while the item is picked
+	pub count: u8, //how many the player has at the start of a battle
+	#[serde(default)]
+	pub heal: i32,
+	#[serde(default)]
+	pub mana: f32,
+	#[serde(default)]
+	pub stamina: f32,
+	#[serde(default)]
+	pub cure: bool, //removes any status effect
+}
+
+#[derive(Deserialize)]
+struct ItemsFile{
+	items: BTreeMap<String, Item>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct ItemId(usize);
+
+#[derive(Debug)]
+pub struct Items{
+	items: Vec<Item>,
+}
+
+impl Items{
+	pub fn load(path: &str) -> Result<Items, String>{
+		let text = fs::read_to_string(path).map_err(|why| format!("Unable to read items {}: {}", path, why))?;
+		let file: ItemsFile = toml::from_str(&text).map_err(|why| format!("Unable to parse items {}: {}", path, why))?;
+		if file.items.len() > MAX_ITEMS {
+			return Err(format!("{} has {} items, there's only room for {}", path, file.items.len(), MAX_ITEMS));
+		}
+		//in name order, so the menu lists them the same way every time
+		Ok(Items{ items: file.items.into_values().collect() })
+	}
+
+	pub fn get(&self, id: ItemId) -> &Item{
+		&self.items[id.0]
+	}
+
+	pub fn ids(&self) -> impl Iterator<Item = ItemId>{
+		(0..self.items.len()).map(ItemId)
+	}
+}
+
+// How many of each item the player has left in a battle
+#[derive(Clone, Copy, Debug)]
+pub struct ItemBag{
+	counts: [u8; MAX_ITEMS],
+}
+
+impl ItemBag{
+	pub fn new(items: &Items) -> ItemBag{
+		let mut counts = [0; MAX_ITEMS];
+		for (count, item) in counts.iter_mut().zip(&items.items) {
+			*count = item.count;
+		}
+		ItemBag{ counts }
+	}
+
+	pub fn count(&self, id: ItemId) -> u8{
+		self.counts[id.0]
+	}
+
+	pub fn take(&mut self, id: ItemId) -> bool{
+		//false if there are none left
+		match self.counts[id.0] {
+			0 => false,
+			_ => {
+				self.counts[id.0] -= 1;
+				true
+			}
+		}
+	}
+}
@@ -4,23 +4,92 @@ mod input;
 mod screens;
 mod sound_manager;
 mod game_context;
+mod timestep;
+mod headless;
+mod replay;
+mod bindings;
+mod controller_manager;
+mod music_director;
+mod assets;
+mod text;
+mod settings;
+mod video;
+mod animation;
+mod game_data;
+mod combat;
+mod enemy;
+mod enemy_ai;
+mod player_stats;
+mod spells;
+mod arena;
+mod physics;
+mod attacks;
+mod items;
+mod rebind;
 
-use std::fs::File;
-use std::io::BufReader;
 use std::time::{Duration, Instant};
-use rodio::{Decoder};
-use sdl2::controller::{Axis, Button};
 use sdl2::event::Event;
-use sdl2::image::{LoadTexture};
-use sdl2::keyboard::Keycode;
 
-use input::{InputState, ControllerType, ControllerSettings, read_input_event};
-use screens::battle::{BattleContext, render_battle};
+use input::{InputState, ControllerSettings, read_input_event};
 use sound_manager::SoundManager;
-use game_context::{GameContext, GameObject, Player};
+use game_context::{GameContext, GameObject};
 use crate::screens::start::StartScreenContext;
+use timestep::FixedTimestep;
+use replay::{InputRecorder, InputReplay};
+use controller_manager::ControllerManager;
+use music_director::MusicDirector;
+use assets::AssetManager;
+use settings::Settings;
+use game_data::GameData;
+use rebind::{Rebinder, RebindStatus};
+
+const DEFAULT_TICKS_PER_SECOND: u32 = 60;
+const MAX_TICKS_PER_FRAME: u32 = 5;
+const MAX_FRAMES_PER_SECOND: Option<u32> = Some(240);
+// Dragging the window edge resizes every frame, settings are only saved once they've stopped changing for this long
+const SETTINGS_SAVE_DELAY: Duration = Duration::from_millis(500);
+
+fn new_game_object() -> GameObject {
+	GameObject{
+		phase: GameContext::StartScreen(StartScreenContext::new()),
+	}
+}
+
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a String> {
+	args.iter()
+		.position(|arg| arg == flag)
+		.map(|flag_index| args.get(flag_index + 1).unwrap_or_else(|| panic!("{} needs a value", flag)))
+}
 
 fn main() {
+	let args: Vec<String> = std::env::args().collect();
+	let mut replay = flag_value(&args, "--replay")
+		.map(|path| InputReplay::load(path).unwrap_or_else(|why| panic!("{}", why)));
+
+	let game_data = GameData::load().unwrap_or_else(|why| panic!("{}", why));
+
+	if args.iter().any(|arg| arg == "--headless") {
+		// Simulate a scripted or replayed run with no window or audio, for machines without a display or sound card
+		let mut game_obj = new_game_object();
+		let result = match replay.as_mut() {
+			Some(replay) => headless::run_replay(&mut game_obj, &game_data, replay),
+			None => {
+				let script_path = flag_value(&args, "--headless").expect("--headless needs a script file path");
+				headless::run_script_file(&mut game_obj, &game_data, script_path)
+			}
+		};
+		match result {
+			Ok(ticks) => {
+				println!("Ran {} ticks, ended in {:?}", ticks, game_obj.phase);
+				return;
+			},
+			Err(why) => {
+				println!("{}", why);
+				std::process::exit(1);
+			}
+		}
+	}
+
 	let sdl_context = sdl2::init().expect("Unable to create sdl context");
 	let controller_subsystem = sdl_context
 		.game_controller()
@@ -29,63 +98,133 @@ fn main() {
 		.video()
 		.expect("Unable to initialize sdl video context");
 
-	let file = BufReader::new(File::open("assets/sounds/Eyewitness.mp3").unwrap());
-	let source = Decoder::new(file).unwrap();
 	let mut my_sound_manager = SoundManager::new();
-	my_sound_manager
-		.play("bg", source)
-		.set_volume(0.5);
-
-	// Attempt to open the controller
-	let _controller = controller_subsystem
-		.open(0)
-		.expect("Failed to open controller");
-	let controller_settings = ControllerSettings::new();
-	let window = video_subsystem.window("Game Window", 1080, 720)
-		.position_centered()
-		.build()
-		.expect("Failed to create window");
-	let mut canvas = window.into_canvas()
-		.software()
-		.build()
-		.expect("Failed to create canvas from window");
+	let mut music_director = MusicDirector::new();
 
-	let texture_creator = canvas.texture_creator();
-	//let background_texture = texture_creator.load_texture("assets/images/background.jpg")
-	//	.expect("Unable to create background texture.");
+	// Pads are opened as SDL reports them, so it's fine to start without one
+	let mut controller_manager = ControllerManager::new(controller_subsystem);
+	let mut controller_settings = ControllerSettings::new();
+	controller_settings.bindings = bindings::Bindings::load_or_default();
+	let mut settings = Settings::load_or_default();
+	controller_settings.stick_response = settings.stick;
+	// Command line overrides only apply to this run, they aren't saved back to the settings file
+	let mut startup_video = settings.video;
+	if args.iter().any(|arg| arg == "--software") {
+		startup_video.renderer = video::RendererBackend::Software;
+	}
+	if args.iter().any(|arg| arg == "--accelerated") {
+		startup_video.renderer = video::RendererBackend::Accelerated;
+	}
+	if args.iter().any(|arg| arg == "--no-vsync") {
+		startup_video.vsync = false;
+	}
+	let (mut canvas, active_renderer) = video::create_canvas(&video_subsystem, &startup_video)
+		.unwrap_or_else(|why| panic!("{}", why));
+	video::apply_video_settings(&mut canvas, &settings.video).unwrap_or_else(|why| println!("Unable to apply video settings: {}", why));
 
-	let background_texture = texture_creator.load_texture("assets/images/neon_noire.png")
-		.expect("Unable to create background texture.");
+	let texture_creator = canvas.texture_creator();
+	let ttf_context = sdl2::ttf::init().expect("Unable to initialize sdl ttf context");
+	let mut assets = AssetManager::new(&texture_creator, &ttf_context);
+	assets.load_defaults(&game_data, &mut my_sound_manager).unwrap_or_else(|why| panic!("{}", why));
 
 	let mut events = sdl_context.event_pump()
 		.expect("Unable to initialize sdl event pump");
-	let target_frame_duration = Duration::from_millis(1000 / 60); // 60 FPS
+	// Replays have to run at the rate they were recorded at to play out the same way
+	let tick_rate = flag_value(&args, "--tick-rate")
+		.map(|rate| match rate.parse::<u32>() {
+			Ok(rate) if rate > 0 => rate,
+			_ => panic!("--tick-rate needs a whole number of ticks per second, got {}", rate),
+		})
+		.unwrap_or(DEFAULT_TICKS_PER_SECOND);
+	let ticks_per_second = replay.as_ref().map(|replay| replay.ticks_per_second).unwrap_or(tick_rate);
+	let mut timestep = FixedTimestep::new(ticks_per_second, MAX_TICKS_PER_FRAME);
+	let mut recorder = flag_value(&args, "--record")
+		.map(|path| InputRecorder::create(path, ticks_per_second).unwrap_or_else(|why| panic!("{}", why)));
+	// Vsync already paces presenting to the display, so only limit the frame rate ourselves without it
+	let min_frame_duration = match active_renderer.vsync {
+		true => None,
+		false => MAX_FRAMES_PER_SECOND.map(|fps| Duration::from_secs(1) / fps),
+	};
 	let mut input_state = InputState::new();
+	let mut previous_tick_input = InputState::new();
 
-	let mut game_obj = GameObject{
-		phase: GameContext::StartScreen(StartScreenContext::new()),
-		player: Some(Player{})
-	};
+	let mut game_obj = new_game_object();
+	let mut previous_game_obj = game_obj.clone();
+	let mut rebinder: Option<Rebinder> = None;
+
+	let mut settings_changed_at: Option<Instant> = None;
 
+	let mut last_frame_start = Instant::now();
 	'mainloop: loop {
 		let frame_start = Instant::now();
+		let frame_elapsed = frame_start - last_frame_start;
+		last_frame_start = frame_start;
 		for event in events.poll_iter() {
-			read_input_event(&mut input_state, &controller_settings, &event);
+			if video::handle_window_event(&mut canvas, &mut settings.video, &event) {
+				settings_changed_at = Some(Instant::now());
+			}
+			controller_manager.handle_event(&mut input_state, &event);
+			// While rebinding, input goes to the rebinder instead of the game
+			let rebinding = rebinder.as_mut()
+				.filter(|_| !matches!(event, Event::Quit { .. }))
+				.map(|rebinder| rebinder.handle_event(&mut controller_settings.bindings, &event));
+			match rebinding {
+				Some(RebindStatus::Running) => (),
+				Some(_) => {
+					rebinder = None;
+					input_state.release_all();
+				},
+				None if rebind::starts_rebinding(&event) => {
+					rebinder = Some(Rebinder::new(&controller_settings.bindings));
+					// anything held now would never see its release
+					input_state.release_all();
+				},
+				None => read_input_event(&mut input_state, &controller_settings, &event),
+			}
+		}
+		let settings_settled = settings_changed_at.is_some_and(|changed_at| changed_at.elapsed() >= SETTINGS_SAVE_DELAY);
+		if settings_settled || (input_state.shutdown && settings_changed_at.is_some()) {
+			settings.save().unwrap_or_else(|why| println!("{}", why));
+			settings_changed_at = None;
 		}
-		println!("{:?}", input_state);
 		if input_state.shutdown{
 			break 'mainloop;
 		}
 
-		game_obj.handle_tick(&input_state, &mut my_sound_manager);
-		game_obj.render(&mut canvas, &background_texture);
+		// The game is frozen while rebinding, the time that passes is thrown away rather than caught up on after
+		timestep.begin_frame();
+		if let Some(rebinder) = rebinder.as_ref() {
+			while timestep.consume_tick() {}
+			rebind::render_rebind(&mut canvas, &mut assets, rebinder, &controller_settings.bindings);
+		}else{
+			// Run as many fixed-length ticks as real time allows, then render between the last two
+			while timestep.consume_tick() {
+				let replayed_input = replay.as_mut().and_then(|replay| replay.next_tick());
+				if replay.is_some() && replayed_input.is_none() {
+					println!("Replay finished, switching to live input");
+					replay = None;
+				}
+				let mut tick_input = replayed_input.unwrap_or(input_state);
+				if let Some(recorder) = recorder.as_mut() {
+					recorder.record(&tick_input);
+				}
+				tick_input.advance_tick(&previous_tick_input);
+				previous_tick_input = tick_input;
+				previous_game_obj = game_obj.clone();
+				game_obj.handle_tick(&tick_input, &mut my_sound_manager, &game_data);
+				// Disconnects are one-off events, only the first tick after one should see it
+				input_state.controller_disconnected = false;
+			}
+			music_director.update(&game_obj.phase, frame_elapsed, &mut my_sound_manager);
+			game_obj.render(&previous_game_obj, timestep.alpha(), &mut canvas, &mut assets, &game_data);
+		}
+		assets.end_frame();
 
-		// Sleep if we finished this frame early so we lock to the desired framerate
-		let frame_duration = frame_start.elapsed();
-		if let Some(remaining_duration) = target_frame_duration.checked_sub(frame_duration) {
-			std::thread::sleep(remaining_duration);
-		} else {
-			println!("Dropped framerate. Frame duration: {:?}, Target: {:?}", frame_duration, target_frame_duration);
+		// Sleep if we finished this frame early so we don't spin rendering the same tick
+		if let Some(min_frame_duration) = min_frame_duration {
+			if let Some(remaining_duration) = min_frame_duration.checked_sub(frame_start.elapsed()) {
+				std::thread::sleep(remaining_duration);
+			}
 		}
 	}
 }
@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::time::Duration;
+use rodio::{Decoder, Source};
+use crate::game_context::GameContext;
+use crate::screens::battle::BattleState;
+use crate::sound_manager::{SoundManager, Bus};
+
+// Picks the music for whatever phase the game is in, crossfading when that changes.
+// Music plays on two decks so the old track can fade out while the new one fades in.
+
+#[derive(Clone, Copy, Debug)]
+pub struct Track {
+	pub path: &'static str,
+	//Some to loop forever, restarting from this point after the first play through (anything before it is the intro)
+	//None to play once and move on to the next track in the playlist
+	pub loop_start: Option<Duration>,
+}
+
+// Which playlist each phase of the game should be playing, None for silence
+fn phase_playlist(phase: &GameContext) -> Option<&'static str> {
+	match phase {
+		GameContext::StartScreen(_) => Some("main_theme"),
+		GameContext::Battle(_) => Some("battle_theme"),
+	}
+}
+
+fn default_playlists() -> HashMap<&'static str, Vec<Track>> {
+	let mut playlists = HashMap::new();
+	playlists.insert("main_theme", vec![
+		Track{ path: "assets/sounds/Eyewitness.mp3", loop_start: Some(Duration::ZERO) },
+	]);
+	//there's no battle music yet, battles crossfade into the theme from the top until there is
+	playlists.insert("battle_theme", vec![
+		Track{ path: "assets/sounds/Eyewitness.mp3", loop_start: Some(Duration::ZERO) },
+	]);
+	playlists
+}
+
+struct Deck {
+	name: &'static str, //sound name the deck plays under in the sound manager
+	playlist: Option<&'static str>,
+	track_index: usize,
+	fade: f32, //0 silent to 1 full volume
+}
+
+pub struct MusicDirector {
+	pub crossfade: Duration,
+	pub volume: f32,
+	pub duck_volume: f32, //how loud the music gets while ducked, as a fraction of volume
+	pub duck_speed: f32, //how much of the duck happens per second
+	pub playlists: HashMap<&'static str, Vec<Track>>,
+	decks: [Deck; 2],
+	current: usize, //the deck that's playing or fading in, the other one is fading out
+	duck: f32,
+}
+
+impl MusicDirector {
+	pub fn new() -> MusicDirector {
+		MusicDirector {
+			crossfade: Duration::from_millis(1500),
+			volume: 0.5,
+			duck_volume: 0.3,
+			duck_speed: 4.0,
+			playlists: default_playlists(),
+			decks: [
+				Deck{ name: "music_a", playlist: None, track_index: 0, fade: 0.0 },
+				Deck{ name: "music_b", playlist: None, track_index: 0, fade: 0.0 },
+			],
+			current: 0,
+			duck: 1.0,
+		}
+	}
+
+	pub fn update(&mut self, phase: &GameContext, elapsed: Duration, sound_manager: &mut SoundManager) {
+		//call once a frame with the real time since the last call
+		if !sound_manager.has_output() {
+			return;
+		}
+
+		if self.switch_to(phase_playlist(phase)) {
+			self.start_track(self.current, sound_manager);
+		}
+		if let Some(finished) = self.step_fades(elapsed) {
+			sound_manager.stop(finished);
+		}
+
+		self.keep_playing(sound_manager);
+
+		//duck under menus so the music doesn't drown them out
+		let ducked = matches!(phase, GameContext::Battle(battle) if matches!(battle.state, BattleState::Paused(_)));
+		let duck_target = if ducked { self.duck_volume } else { 1.0 };
+		let duck_step = elapsed.as_secs_f32() * self.duck_speed;
+		self.duck = if self.duck < duck_target {
+			(self.duck + duck_step).min(duck_target)
+		} else {
+			(self.duck - duck_step).max(duck_target)
+		};
+
+		for deck in &self.decks {
+			sound_manager.set_volume(deck.name, self.volume * deck.fade * self.duck);
+		}
+	}
+
+	fn switch_to(&mut self, wanted: Option<&'static str>) -> bool {
+		//hands the playlist to the other deck to fade in, true if that deck has to start it from the top
+		if wanted == self.decks[self.current].playlist {
+			return false;
+		}
+		self.current = 1 - self.current;
+		//switching back to what the other deck is still fading out reverses the fade instead of starting it over
+		if self.decks[self.current].playlist == wanted {
+			return false;
+		}
+		let deck = &mut self.decks[self.current];
+		deck.playlist = wanted;
+		deck.track_index = 0;
+		deck.fade = 0.0;
+		true
+	}
+
+	fn step_fades(&mut self, elapsed: Duration) -> Option<&'static str> {
+		//moves both decks along the crossfade, returns the outgoing one's name once it's gone silent
+		let fade_step = if self.crossfade.is_zero() { 1.0 } else { elapsed.as_secs_f32() / self.crossfade.as_secs_f32() };
+		let incoming = &mut self.decks[self.current];
+		incoming.fade = (incoming.fade + fade_step).min(1.0);
+		let outgoing = &mut self.decks[1 - self.current];
+		outgoing.fade = (outgoing.fade - fade_step).max(0.0);
+		if outgoing.fade == 0.0 && outgoing.playlist.is_some() {
+			outgoing.playlist = None;
+			return Some(outgoing.name);
+		}
+		None
+	}
+
+	fn current_track(&self, deck_index: usize) -> Option<Track> {
+		let deck = &self.decks[deck_index];
+		deck.playlist
+			.and_then(|playlist| self.playlists.get(playlist))
+			.and_then(|tracks| tracks.get(deck.track_index))
+			.copied()
+	}
+
+	fn start_track(&mut self, deck_index: usize, sound_manager: &mut SoundManager) {
+		let name = self.decks[deck_index].name;
+		let track = match self.current_track(deck_index) {
+			Some(track) => track,
+			None => {
+				sound_manager.stop(name);
+				return;
+			}
+		};
+		match open_track(track.path) {
+			Ok(source) => {
+				sound_manager.play_at_volume(name, Bus::Music, source, 1.0);
+			},
+			Err(why) => {
+				println!("{}", why);
+				self.decks[deck_index].playlist = None;
+			}
+		}
+	}
+
+	fn keep_playing(&mut self, sound_manager: &mut SoundManager) {
+		//tops up looping tracks so they never run dry and moves playlists on when a track ends
+		let deck_index = self.current;
+		let track = match self.current_track(deck_index) {
+			Some(track) => track,
+			None => return,
+		};
+		let name = self.decks[deck_index].name;
+		let queued = sound_manager.queued(name);
+		match track.loop_start {
+			Some(loop_start) if queued < 2 => {
+				//keep the next loop queued up behind the one that's playing so there's no gap
+				let result = open_track(track.path)
+					.and_then(|source| sound_manager.queue(name, source.skip_duration(loop_start)));
+				if let Err(why) = result {
+					println!("{}", why);
+					self.decks[deck_index].playlist = None;
+				}
+			},
+			None if queued == 0 => {
+				let deck = &mut self.decks[deck_index];
+				let playlist_len = deck.playlist.and_then(|playlist| self.playlists.get(playlist)).map(|tracks| tracks.len()).unwrap_or(1);
+				deck.track_index = (deck.track_index + 1) % playlist_len.max(1);
+				self.start_track(deck_index, sound_manager);
+			},
+			_ => ()
+		}
+	}
+}
+
+fn open_track(path: &str) -> Result<Decoder<BufReader<File>>, String> {
+	let file = File::open(path).map_err(|why| format!("Unable to open music {}: {}", path, why))?;
+	Decoder::new(BufReader::new(file)).map_err(|why| format!("Unable to decode music {}: {}", path, why))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::game_data::GameData;
+	use crate::screens::battle::BattleContext;
+	use crate::screens::start::StartScreenContext;
+
+	#[test]
+	fn starting_a_battle_crossfades_from_the_title_music() {
+		let data = GameData::load().unwrap();
+		let mut director = MusicDirector::new();
+		assert!(director.switch_to(phase_playlist(&GameContext::StartScreen(StartScreenContext::new()))));
+		director.step_fades(director.crossfade);
+		assert!(director.switch_to(phase_playlist(&GameContext::Battle(Box::new(BattleContext::new(&data, data.default_arena))))));
+		assert_eq!(director.decks[director.current].playlist, Some("battle_theme"));
+
+		director.step_fades(director.crossfade / 2);
+		assert_eq!(director.decks[director.current].fade, 0.5);
+		assert_eq!(director.decks[1 - director.current].fade, 0.5);
+		assert_eq!(director.step_fades(director.crossfade / 2), Some(director.decks[1 - director.current].name));
+		assert_eq!(director.decks[1 - director.current].playlist, None);
+	}
+
+	#[test]
+	fn switching_back_mid_fade_reverses_it() {
+		let mut director = MusicDirector::new();
+		director.switch_to(Some("main_theme"));
+		director.step_fades(director.crossfade);
+		director.switch_to(Some("battle_theme"));
+		director.step_fades(director.crossfade / 4);
+		assert!(!director.switch_to(Some("main_theme")));
+		assert_eq!(director.decks[director.current].playlist, Some("main_theme"));
+		assert_eq!(director.decks[director.current].fade, 0.75);
+	}
+}
@@ -0,0 +1,176 @@
+use std::ops::{Add, Mul, Sub};
+use sdl2::rect::Point;
+use serde::Deserialize;
+
+// Sub-pixel movement for battle actors. Positions and velocities are floats in logical pixels
+// (per tick for velocities) and only get rounded to whole pixels when something is drawn or hit-tested.
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Vec2{
+	pub x: f32,
+	pub y: f32,
+}
+
+impl Vec2{
+	pub const ZERO: Vec2 = Vec2{ x: 0.0, y: 0.0 };
+
+	pub fn new(x: f32, y: f32) -> Vec2{
+		Vec2{ x, y }
+	}
+
+	pub fn from_angle(angle: f32, length: f32) -> Vec2{
+		//angle uses the facing_vector convention, counterclockwise with up being positive, so y is flipped for the screen
+		Vec2::new(angle.cos() * length, -angle.sin() * length)
+	}
+
+	pub fn length(self) -> f32{
+		self.x.hypot(self.y)
+	}
+
+	pub fn distance(self, other: Vec2) -> f32{
+		(other - self).length()
+	}
+
+	pub fn angle_to(self, other: Vec2) -> f32{
+		//the facing_vector pointing from here to other
+		(self.y - other.y).atan2(other.x - self.x)
+	}
+
+	pub fn lerp(self, other: Vec2, alpha: f32) -> Vec2{
+		self + (other - self) * alpha
+	}
+
+	pub fn to_point(self) -> Point{
+		Point::new(self.x.round() as i32, self.y.round() as i32)
+	}
+}
+
+impl Add for Vec2{
+	type Output = Vec2;
+	fn add(self, other: Vec2) -> Vec2{
+		Vec2::new(self.x + other.x, self.y + other.y)
+	}
+}
+
+impl Sub for Vec2{
+	type Output = Vec2;
+	fn sub(self, other: Vec2) -> Vec2{
+		Vec2::new(self.x - other.x, self.y - other.y)
+	}
+}
+
+impl Mul<f32> for Vec2{
+	type Output = Vec2;
+	fn mul(self, scale: f32) -> Vec2{
+		Vec2::new(self.x * scale, self.y * scale)
+	}
+}
+
+// How a velocity closes in on the velocity an actor wants
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(tag = "curve", rename_all = "snake_case")]
+pub enum Curve{
+	Instant,
+	Linear{ rate: f32 }, //changes by rate pixels per tick every tick
+	Exponential{ rate: f32 }, //covers this share of the remaining difference every tick, quick at first then easing in
+}
+
+impl Curve{
+	pub fn approach(&self, current: Vec2, target: Vec2) -> Vec2{
+		let difference = target - current;
+		let step = match *self {
+			Curve::Instant => return target,
+			Curve::Linear{ rate } => rate,
+			Curve::Exponential{ rate } => difference.length() * rate.clamp(0.0, 1.0),
+		};
+		//snap once it's close so it doesn't creep forever
+		if difference.length() <= step.max(0.01) {
+			target
+		}else{
+			current + difference * (step / difference.length())
+		}
+	}
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct MovementStats{
+	pub acceleration: Curve, //used while speeding up
+	pub deceleration: Curve, //used while slowing down or stopping
+	pub friction: f32, //share of velocity lost each tick while the actor is stunned and coasting to a stop
+}
+
+impl Default for MovementStats{
+	fn default() -> MovementStats{
+		MovementStats{
+			acceleration: Curve::Instant,
+			deceleration: Curve::Instant,
+			friction: 0.15,
+		}
+	}
+}
+
+impl MovementStats{
+	pub fn steer(&self, velocity: Vec2, target: Vec2) -> Vec2{
+		let curve = match target.length() >= velocity.length() {
+			true => self.acceleration,
+			false => self.deceleration,
+		};
+		curve.approach(velocity, target)
+	}
+
+	pub fn drift(&self, velocity: Vec2) -> Vec2{
+		let slowed = velocity * (1.0 - self.friction.clamp(0.0, 1.0));
+		if slowed.length() < 0.01 { Vec2::ZERO } else { slowed }
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn linear_moves_a_fixed_step_then_snaps(){
+		let curve = Curve::Linear{ rate: 1.0 };
+		let target = Vec2::new(2.5, 0.0);
+		let first = curve.approach(Vec2::ZERO, target);
+		assert!((first.x - 1.0).abs() < 0.0001);
+		let second = curve.approach(first, target);
+		assert!((second.x - 2.0).abs() < 0.0001);
+		assert_eq!(curve.approach(second, target), target);
+	}
+
+	#[test]
+	fn exponential_covers_a_share_of_what_is_left(){
+		let curve = Curve::Exponential{ rate: 0.5 };
+		let target = Vec2::new(0.0, -8.0);
+		let first = curve.approach(Vec2::ZERO, target);
+		assert!((first.y + 4.0).abs() < 0.0001);
+		let second = curve.approach(first, target);
+		assert!((second.y + 6.0).abs() < 0.0001);
+		//it snaps instead of creeping forever
+		let mut velocity = second;
+		for _ in 0..20 {
+			velocity = curve.approach(velocity, target);
+		}
+		assert_eq!(velocity, target);
+	}
+
+	#[test]
+	fn instant_jumps_straight_there(){
+		let target = Vec2::new(3.0, 4.0);
+		assert_eq!(Curve::Instant.approach(Vec2::new(-1.0, 0.0), target), target);
+	}
+
+	#[test]
+	fn steers_with_deceleration_when_slowing_down(){
+		let movement = MovementStats{
+			acceleration: Curve::Linear{ rate: 1.0 },
+			deceleration: Curve::Instant,
+			friction: 0.5,
+		};
+		assert_eq!(movement.steer(Vec2::ZERO, Vec2::new(4.0, 0.0)), Vec2::new(1.0, 0.0));
+		assert_eq!(movement.steer(Vec2::new(4.0, 0.0), Vec2::ZERO), Vec2::ZERO);
+		assert_eq!(movement.drift(Vec2::new(4.0, 0.0)), Vec2::new(2.0, 0.0));
+		assert_eq!(movement.drift(Vec2::new(0.015, 0.0)), Vec2::ZERO);
+	}
+}
@@ -0,0 +1,67 @@
+use std::fs;
+use serde::Deserialize;
+use crate::physics::MovementStats;
+
+// The player's battle numbers, loaded from assets/data/player.toml so they can be tuned without a rebuild.
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct GuardStats{
+	pub max: f32,
+	pub per_damage: f32,
+	pub damage_multiplier: f32,
+	pub regen: f32,
+	pub regen_delay: usize,
+	pub parry_window: usize,
+	pub parry_stagger: usize,
+	pub block_arc: f32, //degrees, centred on where the player faces
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct StaminaStats{
+	pub max: f32,
+	pub regen: f32,
+	pub regen_delay: usize,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct DashStats{
+	pub ticks: usize,
+	pub cost: f32,
+	pub cooldown: usize,
+	pub invulnerable_start: usize,
+	pub invulnerable_end: usize,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct CastingStats{
+	pub max_mana: f32,
+	pub mana_regen: f32,
+	pub spell: String, //the spell cast when a battle starts
+	pub known_spells: Vec<String>, //the spells the pause menu can equip
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct MovementSpeeds{
+	pub run_speed: f32, //pixels per tick at full tilt
+	pub dash_speed: f32,
+	#[serde(flatten)]
+	pub stats: MovementStats,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct PlayerStats{
+	pub max_health: i32,
+	pub invulnerability: usize,
+	pub movement: MovementSpeeds,
+	pub stamina: StaminaStats,
+	pub dash: DashStats,
+	pub guard: GuardStats,
+	pub casting: CastingStats,
+}
+
+impl PlayerStats{
+	pub fn load(path: &str) -> Result<PlayerStats, String>{
+		let text = fs::read_to_string(path).map_err(|why| format!("Unable to read player stats {}: {}", path, why))?;
+		toml::from_str(&text).map_err(|why| format!("Unable to parse player stats {}: {}", path, why))
+	}
+}
@@ -0,0 +1,155 @@
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::WindowCanvas;
+use crate::assets::AssetManager;
+use crate::bindings::Bindings;
+use crate::input::{InputAxis, InputButton};
+use crate::text::{draw_text, Align, TextStyle};
+
+// Changing bindings in game. F9 walks through every logical button and then every axis in turn.
+// The first key or pad button pressed for a button replaces what it had, any more pressed before moving on are added to it,
+// and axes are bound the same way by pushing a stick or trigger most of the way.
+// Tab moves on to the next one, keeping its bindings if nothing was pressed, and Escape stops without saving.
+// The bindings are saved to the user config directory once the last one is done.
+
+const AXIS_THRESHOLD: i16 = 24000; //how far a stick or trigger has to move to be picked
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Prompt{
+	Button(InputButton),
+	Axis(InputAxis),
+}
+
+// The triggers are bound as axes, their buttons follow from those
+const PROMPTS: [Prompt; 20] = [
+	Prompt::Button(InputButton::DpadUp),
+	Prompt::Button(InputButton::DpadDown),
+	Prompt::Button(InputButton::DpadLeft),
+	Prompt::Button(InputButton::DpadRight),
+	Prompt::Button(InputButton::Up),
+	Prompt::Button(InputButton::Down),
+	Prompt::Button(InputButton::Left),
+	Prompt::Button(InputButton::Right),
+	Prompt::Button(InputButton::LeftShoulder),
+	Prompt::Button(InputButton::RightShoulder),
+	Prompt::Button(InputButton::Start),
+	Prompt::Button(InputButton::Back),
+	Prompt::Button(InputButton::LeftStick),
+	Prompt::Button(InputButton::RightStick),
+	Prompt::Axis(InputAxis::LeftX),
+	Prompt::Axis(InputAxis::LeftY),
+	Prompt::Axis(InputAxis::RightX),
+	Prompt::Axis(InputAxis::RightY),
+	Prompt::Axis(InputAxis::TriggerLeft),
+	Prompt::Axis(InputAxis::TriggerRight),
+];
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RebindStatus{
+	Running,
+	Finished,
+	Cancelled,
+}
+
+pub struct Rebinder{
+	step: usize,
+	replaced: bool, //whether this step's old bindings have been cleared yet
+	before: Bindings, //put back if the player cancels
+}
+
+pub fn starts_rebinding(event: &Event) -> bool{
+	matches!(event, Event::KeyDown {keycode: Some(Keycode::F9), repeat: false, ..})
+}
+
+impl Rebinder{
+	pub fn new(bindings: &Bindings) -> Rebinder{
+		Rebinder{
+			step: 0,
+			replaced: false,
+			before: bindings.clone(),
+		}
+	}
+
+	pub fn handle_event(&mut self, bindings: &mut Bindings, event: &Event) -> RebindStatus{
+		match (event, PROMPTS[self.step]) {
+			(Event::KeyDown {keycode: Some(Keycode::Escape), ..}, _) => {
+				*bindings = self.before.clone();
+				return RebindStatus::Cancelled;
+			},
+			(Event::KeyDown {keycode: Some(Keycode::Tab), repeat: false, ..}, _) => return self.next(bindings),
+			(Event::KeyDown {keycode: Some(key), repeat: false, ..}, Prompt::Button(button)) => {
+				self.replace(bindings);
+				bindings.bind_key(button, *key);
+			},
+			(Event::ControllerButtonDown {button: pad_button, ..}, Prompt::Button(button)) => {
+				self.replace(bindings);
+				bindings.bind_gamepad_button(button, *pad_button);
+			},
+			(Event::ControllerAxisMotion {axis: pad_axis, value, ..}, Prompt::Axis(axis)) if value.unsigned_abs() > AXIS_THRESHOLD as u16 => {
+				self.replace(bindings);
+				bindings.bind_gamepad_axis(axis, *pad_axis);
+			},
+			_ => (),
+		}
+		RebindStatus::Running
+	}
+
+	fn replace(&mut self, bindings: &mut Bindings){
+		if self.replaced {
+			return;
+		}
+		match PROMPTS[self.step] {
+			Prompt::Button(button) => bindings.clear_bindings(button),
+			Prompt::Axis(axis) => bindings.clear_axis_bindings(axis),
+		}
+		self.replaced = true;
+	}
+
+	fn next(&mut self, bindings: &Bindings) -> RebindStatus{
+		self.step += 1;
+		self.replaced = false;
+		if self.step < PROMPTS.len() {
+			return RebindStatus::Running;
+		}
+		if let Err(why) = bindings.save() {
+			println!("Unable to save bindings: {}", why);
+		}
+		RebindStatus::Finished
+	}
+}
+
+pub fn render_rebind(canvas: &mut WindowCanvas, assets: &mut AssetManager, rebinder: &Rebinder, bindings: &Bindings){
+	let (width, height) = canvas.logical_size();
+	canvas.set_draw_color(Color::RGB(0, 0, 40));
+	canvas.clear();
+
+	let (name, bound) = match PROMPTS[rebinder.step] {
+		Prompt::Button(button) => {
+			let keys = bindings.keyboard.get(&button).into_iter().flatten();
+			let pad = bindings.gamepad_buttons.get(&button).into_iter().flatten();
+			(format!("{:?}", button), keys.chain(pad).cloned().collect::<Vec<String>>())
+		},
+		Prompt::Axis(axis) => (format!("{:?}", axis), bindings.gamepad_axes.get(&axis).cloned().unwrap_or_default()),
+	};
+	let instructions = match PROMPTS[rebinder.step] {
+		Prompt::Button(_) => "Press keys or pad buttons to bind",
+		Prompt::Axis(_) => "Push a stick or trigger to bind",
+	};
+	let bound = if bound.is_empty() { "nothing".to_string() } else { bound.join(", ") };
+
+	let font = assets.font("frontman").expect("Rebind font not loaded");
+	let lines = [
+		(TextStyle::new(font, 48, Color::RGBA(255, 225, 0, 255)), format!("Controls {}/{}", rebinder.step + 1, PROMPTS.len())),
+		(TextStyle::new(font, 64, Color::RGBA(255, 64, 0, 255)), name),
+		(TextStyle::new(font, 36, Color::RGBA(255, 255, 255, 255)), format!("Bound to {}", bound)),
+		(TextStyle::new(font, 28, Color::RGBA(140, 140, 140, 255)), format!("{}, Tab for the next, Escape to cancel", instructions)),
+	];
+	let margin = width / 10;
+	for (i, (style, text)) in lines.iter().enumerate() {
+		let row = Rect::new(margin as i32, (height / 5) as i32 + i as i32 * 96, width - margin * 2, 80);
+		draw_text(canvas, assets, &style.align(Align::Center), text, row).expect("Couldn't write rebind text.");
+	}
+	canvas.present();
+}
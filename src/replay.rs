@@ -0,0 +1,243 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use crate::input::{InputState, ControllerType};
+
+// Replay files hold the input state the simulation saw on every tick.
+// Layout: the magic bytes, a format version, the tick rate it was recorded at,
+// then runs of (tick count: u16, encoded input state) for each stretch of identical ticks.
+// Everything is little endian.
+
+const MAGIC: &[u8; 4] = b"G2RP";
+const VERSION: u8 = 2;
+const ENCODED_INPUT_LEN: usize = 16;
+
+pub struct InputRecorder{
+	writer: BufWriter<File>,
+	last_state: Option<InputState>,
+	repeat_count: u16,
+}
+
+impl InputRecorder{
+	pub fn create(path: &str, ticks_per_second: u32) -> Result<InputRecorder, String>{
+		let file = File::create(path).map_err(|why| format!("Unable to create replay file {}: {}", path, why))?;
+		let mut writer = BufWriter::new(file);
+		let mut header = Vec::new();
+		header.extend_from_slice(MAGIC);
+		header.push(VERSION);
+		header.extend_from_slice(&ticks_per_second.to_le_bytes());
+		writer.write_all(&header).map_err(|why| format!("Unable to write replay header: {}", why))?;
+		Ok(InputRecorder{
+			writer,
+			last_state: None,
+			repeat_count: 0,
+		})
+	}
+
+	pub fn record(&mut self, input: &InputState){
+		//call once per simulated tick with the state that tick was given
+		match self.last_state {
+			Some(last) if same_input(&last, input) && self.repeat_count < u16::MAX => {
+				self.repeat_count += 1;
+			},
+			_ => {
+				self.flush_run();
+				self.last_state = Some(*input);
+				self.repeat_count = 1;
+			}
+		}
+	}
+
+	fn flush_run(&mut self){
+		if let Some(last) = self.last_state {
+			let mut run = Vec::with_capacity(2 + ENCODED_INPUT_LEN);
+			run.extend_from_slice(&self.repeat_count.to_le_bytes());
+			run.extend_from_slice(&encode_input(&last));
+			if let Err(why) = self.writer.write_all(&run) {
+				println!("Unable to write to replay file: {}", why);
+			}
+		}
+		self.last_state = None;
+		self.repeat_count = 0;
+	}
+
+	pub fn finish(&mut self){
+		self.flush_run();
+		if let Err(why) = self.writer.flush() {
+			println!("Unable to write to replay file: {}", why);
+		}
+	}
+}
+
+impl Drop for InputRecorder{
+	fn drop(&mut self){
+		self.finish();
+	}
+}
+
+pub struct InputReplay{
+	pub ticks_per_second: u32,
+	runs: Vec<(u16, InputState)>,
+	run_index: usize,
+	ticks_into_run: u16,
+}
+
+impl InputReplay{
+	pub fn load(path: &str) -> Result<InputReplay, String>{
+		let file = File::open(path).map_err(|why| format!("Unable to open replay file {}: {}", path, why))?;
+		let mut bytes = Vec::new();
+		BufReader::new(file).read_to_end(&mut bytes).map_err(|why| format!("Unable to read replay file {}: {}", path, why))?;
+
+		if bytes.len() < 9 || &bytes[0..4] != MAGIC {
+			return Err(format!("{} is not a replay file", path));
+		}
+		if bytes[4] != VERSION {
+			return Err(format!("{} is replay format version {}, expected {}", path, bytes[4], VERSION));
+		}
+		let ticks_per_second = u32::from_le_bytes([bytes[5], bytes[6], bytes[7], bytes[8]]);
+
+		let mut runs = Vec::new();
+		let mut chunks = bytes[9..].chunks_exact(2 + ENCODED_INPUT_LEN);
+		for chunk in &mut chunks {
+			let count = u16::from_le_bytes([chunk[0], chunk[1]]);
+			runs.push((count, decode_input(&chunk[2..])));
+		}
+		if !chunks.remainder().is_empty() {
+			return Err(format!("{} ends partway through a tick, the file is truncated", path));
+		}
+		Ok(InputReplay{
+			ticks_per_second,
+			runs,
+			run_index: 0,
+			ticks_into_run: 0,
+		})
+	}
+
+	pub fn next_tick(&mut self) -> Option<InputState>{
+		//the input for the next tick, None once the recording has run out
+		while let Some((count, input)) = self.runs.get(self.run_index) {
+			if self.ticks_into_run < *count {
+				self.ticks_into_run += 1;
+				return Some(*input);
+			}
+			self.run_index += 1;
+			self.ticks_into_run = 0;
+		}
+		None
+	}
+}
+
+fn same_input(a: &InputState, b: &InputState) -> bool{
+	encode_input(a) == encode_input(b)
+}
+
+fn encode_input(input: &InputState) -> [u8; ENCODED_INPUT_LEN]{
+	let mut bytes = [0u8; ENCODED_INPUT_LEN];
+	let axes = [
+		input.left_x_pos, input.left_y_pos,
+		input.right_x_pos, input.right_y_pos,
+		input.trig_left_pos, input.trig_right_pos,
+	];
+	for (i, axis) in axes.iter().enumerate() {
+		bytes[i*2..i*2+2].copy_from_slice(&axis.to_le_bytes());
+	}
+	let flags = [
+		input.left_shoulder, input.right_shoulder,
+		input.dpad_up, input.dpad_down, input.dpad_left, input.dpad_right,
+		input.btn_left, input.btn_right, input.btn_up, input.btn_down,
+		input.btn_start, input.btn_back,
+		input.left_stick, input.right_stick,
+		matches!(input.device, ControllerType::Gamepad),
+		input.controller_disconnected,
+		input.left_trigger, input.right_trigger,
+	];
+	let mut packed: u32 = 0;
+	for (i, flag) in flags.iter().enumerate() {
+		if *flag {
+			packed |= 1 << i;
+		}
+	}
+	bytes[12..16].copy_from_slice(&packed.to_le_bytes());
+	bytes
+}
+
+fn decode_input(bytes: &[u8]) -> InputState{
+	let axis = |i: usize| i16::from_le_bytes([bytes[i*2], bytes[i*2+1]]);
+	let packed = u32::from_le_bytes([bytes[12], bytes[13], bytes[14], bytes[15]]);
+	let flag = |i: usize| packed & (1 << i) != 0;
+	let mut input = InputState::new();
+	input.left_x_pos = axis(0);
+	input.left_y_pos = axis(1);
+	input.right_x_pos = axis(2);
+	input.right_y_pos = axis(3);
+	input.trig_left_pos = axis(4);
+	input.trig_right_pos = axis(5);
+	input.left_shoulder = flag(0);
+	input.right_shoulder = flag(1);
+	input.dpad_up = flag(2);
+	input.dpad_down = flag(3);
+	input.dpad_left = flag(4);
+	input.dpad_right = flag(5);
+	input.btn_left = flag(6);
+	input.btn_right = flag(7);
+	input.btn_up = flag(8);
+	input.btn_down = flag(9);
+	input.btn_start = flag(10);
+	input.btn_back = flag(11);
+	input.left_stick = flag(12);
+	input.right_stick = flag(13);
+	input.device = if flag(14) { ControllerType::Gamepad } else { ControllerType::Keyboard };
+	input.controller_disconnected = flag(15);
+	input.left_trigger = flag(16);
+	input.right_trigger = flag(17);
+	input
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn input_survives_encoding(){
+		let mut input = InputState::new();
+		input.left_x_pos = -32768;
+		input.right_y_pos = 32767;
+		input.trig_right_pos = 1234;
+		input.dpad_left = true;
+		input.btn_start = true;
+		input.right_trigger = true;
+		input.device = ControllerType::Gamepad;
+		let decoded = decode_input(&encode_input(&input));
+		assert!(same_input(&input, &decoded));
+		assert_eq!(decoded.left_x_pos, -32768);
+		assert!(decoded.dpad_left && decoded.btn_start && decoded.right_trigger);
+		assert!(!decoded.dpad_right && !decoded.left_trigger);
+	}
+
+	#[test]
+	fn long_runs_play_back_tick_for_tick(){
+		//a run longer than a u16 count has to be split across several runs in the file
+		let path = std::env::temp_dir().join(format!("game2_replay_test_{}.replay", std::process::id()));
+		let path = path.to_str().unwrap();
+		let mut held = InputState::new();
+		held.left_x_pos = 32767;
+		let mut released = InputState::new();
+		released.btn_down = true;
+		let held_ticks = u16::MAX as usize * 2 + 10;
+		{
+			let mut recorder = InputRecorder::create(path, 120).unwrap();
+			for _ in 0..held_ticks {
+				recorder.record(&held);
+			}
+			recorder.record(&released);
+		}
+		let mut replay = InputReplay::load(path).unwrap();
+		std::fs::remove_file(path).unwrap();
+		assert_eq!(replay.ticks_per_second, 120);
+		assert_eq!(replay.runs.len(), 4);
+		for _ in 0..held_ticks {
+			assert!(same_input(&replay.next_tick().unwrap(), &held));
+		}
+		assert!(same_input(&replay.next_tick().unwrap(), &released));
+		assert!(replay.next_tick().is_none());
+	}
+}
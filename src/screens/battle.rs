@@ -1,67 +1,241 @@
-use std::fs::File;
-use std::io::BufReader;
 use sdl2::pixels::Color;
 use sdl2::rect::{Point, Rect};
-use sdl2::render::{WindowCanvas, Texture, BlendMode};
-use rodio::{Decoder};
+use sdl2::render::{WindowCanvas, BlendMode};
+use crate::animation::{AnimationState, SpriteSheet};
+use crate::arena::{to_rect, Arena, ArenaId, Obstacle};
+use crate::assets::AssetManager;
+use crate::attacks::{find_link, AttackId, AttackInput, Attacks, Link};
+use crate::combat::{angle_between, attack_hitbox, check_hit, circle_hurts, hurtbox, Hit, Hitbox, Target, Vitals};
+use crate::enemy::{Enemy, MAX_ENEMIES};
+use crate::enemy_ai::Action;
 use crate::game_context::{GameContext, GameObject};
-use crate::input::{InputState, get_player_intent_vector};
+use crate::game_data::GameData;
+use crate::input::{InputState, InputButton, get_player_intent_vector};
+use crate::items::{Item, ItemBag};
+use crate::physics::Vec2;
+use crate::player_stats::{GuardStats, PlayerStats};
+use crate::screens::pause_menu::{render_pause_menu, MenuAction, PauseMenu};
+use crate::screens::start::StartScreenContext;
 use crate::sound_manager::SoundManager;
+use crate::spells::{Projectile, SpellId, Spells, MAX_PROJECTILES};
+use crate::text::{draw_text, Align, TextStyle};
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
 pub enum BattleState{
-	Starting,
 	Live,
-	Paused, //paused for quick-menu selection
-	Finished
+	Paused(PauseMenu), //frozen while the pause menu is open
+	Finished(BattleOutcome)
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BattleOutcome{
+	Victory,
+	Defeat
+}
+
+#[derive(Clone, Copy, Debug)]
 pub struct BattleContext{
 	pub state: BattleState,
 	pub player: BattlePlayerContext,
+	pub enemies: [Option<Enemy>; MAX_ENEMIES], //defeated enemies leave an empty slot so the others keep their index
+	pub projectiles: [Option<Projectile>; MAX_PROJECTILES],
+	pub arena: ArenaId,
+	pub items: ItemBag, //what's left of the player's items
 }
 impl BattleContext{
-	pub fn new() -> BattleContext{
+	pub fn new(data: &GameData, arena_id: ArenaId) -> BattleContext{
+		let arena = data.arenas.get(arena_id);
+		if arena.spawns.len() > MAX_ENEMIES {
+			println!("Arena has {} enemies, only the first {} are spawned", arena.spawns.len(), MAX_ENEMIES);
+		}
+		let mut enemies = [None; MAX_ENEMIES];
+		for (slot, spawn) in enemies.iter_mut().zip(&arena.spawns) {
+			let kind = data.enemy_types.kind(&spawn.enemy).expect("Arena spawns are checked when the game data loads");
+			*slot = Some(Enemy::new(kind, &data.enemy_types, Vec2::new(spawn.position[0] as f32, spawn.position[1] as f32)));
+		}
+		let player_start = Vec2::new(arena.player_start[0] as f32, arena.player_start[1] as f32);
 		BattleContext{
 			state: BattleState::Live,//TODO change this to starting once we have state transitions
-			player:BattlePlayerContext{
-				position: Point::new(50,300),
-				facing_vector: 0.0,
-				state: PlayerState::Standing
+			player: BattlePlayerContext::new(player_start, &data.player_stats, data.player_spell),
+			enemies,
+			projectiles: [None; MAX_PROJECTILES],
+			arena: arena_id,
+			items: ItemBag::new(&data.items),
+		}
+	}
+	pub fn from_game_object(_game_object: &GameObject, data: &GameData) -> BattleContext{
+		BattleContext::new(data, data.default_arena)
+	}
+	fn update_live(&mut self, input_state: &InputState, sound_manager: &mut SoundManager, data: &GameData){
+		//everyone moves, then every hit from this tick is applied at once
+		let arena = data.arenas.get(self.arena);
+		update_battle_player(&mut self.player, input_state, sound_manager, data, arena);
+		if released_spell(&self.player, &data.player_sprites) {
+			let aim = aim_angle(input_state).unwrap_or(self.player.facing_vector);
+			let position = self.player.position;
+			self.spawn_projectile(Projectile::new(self.player.spell, &data.spells, Target::Player, position.x, position.y, aim));
+		}
+		let mut hits = Vec::new();
+		if let Some(hitbox) = player_hitbox(&self.player, &data.attacks) {
+			for (i, enemy) in self.enemies.iter().enumerate() {
+				if let Some(hit) = enemy.and_then(|enemy| check_hit(Target::Player, self.player.position.to_point(), &hitbox, Target::Enemy(i), enemy.hurtbox())) {
+					hits.push(hit);
+				}
+			}
+		}
+		for (i, enemy) in self.enemies.iter_mut().enumerate() {
+			if let Some(enemy) = enemy {
+				if let Some(hitbox) = enemy.update(&self.player, &data.enemy_types, arena) {
+					if let Some(hit) = check_hit(Target::Enemy(i), enemy.position.to_point(), &hitbox, Target::Player, self.player.hurtbox()) {
+						hits.push(hit);
+					}
+				}
+			}
+		}
+		self.update_projectiles(&data.spells, arena, &mut hits);
+		self.resolve_hits(&hits, &data.player_stats);
+		for slot in self.enemies.iter_mut() {
+			if slot.is_some_and(|enemy| enemy.vitals.is_dead()) {
+				*slot = None;
 			}
 		}
+		if let Some(outcome) = self.outcome() {
+			self.state = BattleState::Finished(outcome);
+		}
 	}
-	pub fn from_game_object(game_object: &GameObject) -> BattleContext{
-		BattleContext{
-			state: BattleState::Live,//TODO change this to starting once we have state transitions
-			player:BattlePlayerContext{
-				position: Point::new(50,300),
-				facing_vector: 0.0,
-				state: PlayerState::Standing
+	fn resolve_hits(&mut self, hits: &[Hit], stats: &PlayerStats){
+		let guard = &stats.guard;
+		for hit in hits {
+			match (hit.target, self.player.state) {
+				(Target::Player, _) if dodging(&self.player, stats) => (),
+				(Target::Player, PlayerState::Blocking(ticks)) if ticks < guard.parry_window && facing_hit(&self.player, hit, guard) => {
+					//parried, the attacker is the one that gets knocked back
+					if let Target::Enemy(i) = hit.attacker {
+						if let Some(enemy) = self.enemies[i].as_mut() {
+							enemy.vitals.knock_back(hit.knockback_vector + std::f32::consts::PI, guard.parry_stagger);
+						}
+					}
+				},
+				(Target::Player, PlayerState::Blocking(_)) if facing_hit(&self.player, hit, guard) => {
+					if self.player.vitals.is_invulnerable() {
+						continue;
+					}
+					self.player.guard = (self.player.guard - hit.damage as f32 * guard.per_damage).max(0.0);
+					self.player.guard_cooldown = guard.regen_delay;
+					if self.player.guard > 0.0 {
+						//blocked, only part of the damage gets through and the player keeps blocking
+						let damage = (hit.damage as f32 * guard.damage_multiplier).round() as i32;
+						self.player.vitals.take_blocked_hit(damage);
+					}else{
+						//the guard broke, the whole hit lands
+						self.player.hit(hit, stats);
+					}
+				},
+				(Target::Player, _) => self.player.hit(hit, stats),
+				(Target::Enemy(i), _) => {
+					if let Some(enemy) = self.enemies[i].as_mut() {
+						enemy.vitals.take_hit(hit);
+					}
+				},
+			}
+		}
+	}
+	fn spawn_projectile(&mut self, projectile: Projectile){
+		match self.projectiles.iter_mut().find(|slot| slot.is_none()) {
+			Some(slot) => *slot = Some(projectile),
+			None => println!("Too many projectiles, dropping one"),
+		}
+	}
+	fn targets_of(&self, owner: Target) -> Vec<(Target, Rect)>{
+		//everyone on the other side from owner, with their hurtboxes
+		match owner {
+			Target::Player => self.enemies.iter().enumerate()
+				.filter_map(|(i, enemy)| enemy.map(|enemy| (Target::Enemy(i), enemy.hurtbox())))
+				.collect(),
+			Target::Enemy(_) => vec![(Target::Player, self.player.hurtbox())],
+		}
+	}
+	fn update_projectiles(&mut self, spells: &Spells, arena: &Arena, hits: &mut Vec<Hit>){
+		//moves every projectile, and explodes the ones that touched someone into hits
+		for i in 0..MAX_PROJECTILES {
+			let mut projectile = match self.projectiles[i] {
+				Some(projectile) => projectile,
+				None => continue
+			};
+			projectile.advance(spells);
+			let spell = spells.get(projectile.spell);
+			let center = Point::new(projectile.x.round() as i32, projectile.y.round() as i32);
+			let targets = self.targets_of(projectile.owner);
+			let impact = targets.iter().any(|(_, hurtbox)| circle_hurts(center, spell.radius, *hurtbox));
+			if impact {
+				let blast_radius = spell.radius.max(spell.area);
+				for (target, hurtbox) in targets.iter().filter(|(_, hurtbox)| circle_hurts(center, blast_radius, *hurtbox)) {
+					hits.push(Hit{
+						attacker: projectile.owner,
+						target: *target,
+						damage: spell.damage,
+						knockback_vector: angle_between(center, hurtbox.center()),
+						knockback_ticks: spell.knockback_ticks,
+						status: spell.status,
+					});
+				}
 			}
+			let hit_wall = arena.blocks(Rect::from_center(center, spell.radius * 2, spell.radius * 2));
+			self.projectiles[i] = match impact || hit_wall || projectile.ticks_left == 0 {
+				true => None,
+				false => Some(projectile),
+			};
+		}
+	}
+	pub fn outcome(&self) -> Option<BattleOutcome>{
+		if self.player.vitals.is_dead() {
+			Some(BattleOutcome::Defeat)
+		}else if self.enemies.iter().all(|enemy| enemy.is_none()) {
+			Some(BattleOutcome::Victory)
+		}else{
+			None
 		}
 	}
-	pub fn handle_tick(game_obj: &mut GameObject, input_state: &InputState, my_sound_manager: &mut SoundManager){
+	pub fn handle_tick(game_obj: &mut GameObject, input_state: &InputState, my_sound_manager: &mut SoundManager, data: &GameData){
 		match game_obj.phase {
 			GameContext::Battle(ref mut battle_context) =>{
-				let battle_player = &mut battle_context.player;
 				match battle_context.state {
-					BattleState::Starting => (),
 					BattleState::Live => {
-						if input_state.btn_start{
-							battle_context.state = BattleState::Paused;
+						if input_state.just_pressed(InputButton::Start) || input_state.controller_disconnected{
+							battle_context.state = BattleState::Paused(PauseMenu::new(input_state));
 						}else{
-							update_battle_player(battle_player, &input_state, my_sound_manager);
+							battle_context.update_live(input_state, my_sound_manager, data);
 						}
 
 					},
-					BattleState::Paused => {
-						if input_state.btn_start{
-							battle_context.state = BattleState::Live;
+					BattleState::Paused(_) if input_state.just_pressed(InputButton::Start) => {
+						battle_context.state = BattleState::Live;
+					},
+					BattleState::Paused(mut menu) => {
+						//nothing in the battle moves while the menu is open, only what the menu asks for changes
+						match menu.update(input_state, data, my_sound_manager) {
+							MenuAction::Stay => battle_context.state = BattleState::Paused(menu),
+							MenuAction::Resume => battle_context.state = BattleState::Live,
+							MenuAction::UseItem(item) => {
+								if battle_context.items.take(item) {
+									battle_context.player.use_item(data.items.get(item), &data.player_stats);
+								}
+								battle_context.state = BattleState::Paused(menu);
+							},
+							MenuAction::Equip(spell) => {
+								battle_context.player.spell = spell;
+								battle_context.state = BattleState::Paused(menu);
+							},
+							MenuAction::Retreat => {
+								game_obj.phase = GameContext::StartScreen(StartScreenContext::new());
+							},
+						}
+					},
+					BattleState::Finished(_) => {
+						if input_state.just_pressed(InputButton::Start){
+							game_obj.phase = GameContext::StartScreen(StartScreenContext::new());
 						}
 					},
-					BattleState::Finished => (),
 				}
 			},
 			_ => unreachable!("Should not be able to call handle_tick from start screen while not in battle phase.")
@@ -69,127 +243,611 @@ impl BattleContext{
 	}
 }
 
-#[derive(Clone, Copy)]
+const PLAYER_SIZE: (u32, u32) = (30, 40); //the player's hurtbox, also what collides with the arena
+
+#[derive(Clone, Copy, Debug)]
 pub struct BattlePlayerContext{
-	pub position: Point,
+	pub position: Vec2,
+	pub velocity: Vec2,
 	pub facing_vector: f32,
 	pub state: PlayerState,
+	pub animation: AnimationState,
+	pub hitbox_active: bool, //whether the current attack can hit, out between its hit_start and hit_end in attacks.toml
+	pub vitals: Vitals,
+	pub guard: f32,
+	pub guard_cooldown: usize, //ticks until the guard starts coming back
+	pub mana: f32,
+	pub stamina: f32,
+	pub stamina_cooldown: usize, //ticks until stamina starts coming back
+	pub dash_cooldown: usize, //ticks until the player can dash again
+	pub buffered_attack: Option<AttackInput>, //the last attack pressed, kept until it's used or buffer_ticks runs out
+	pub buffer_ticks: usize,
+	pub spell: SpellId, //what casting fires
+}
+impl BattlePlayerContext{
+	pub fn new(position: Vec2, stats: &PlayerStats, spell: SpellId) -> BattlePlayerContext{
+		BattlePlayerContext{
+			position,
+			velocity: Vec2::ZERO,
+			facing_vector: 0.0,
+			state: PlayerState::Standing,
+			animation: AnimationState::new(),
+			hitbox_active: false,
+			vitals: Vitals::new(stats.max_health, stats.invulnerability),
+			guard: stats.guard.max,
+			guard_cooldown: 0,
+			mana: stats.casting.max_mana,
+			stamina: stats.stamina.max,
+			stamina_cooldown: 0,
+			dash_cooldown: 0,
+			buffered_attack: None,
+			buffer_ticks: 0,
+			spell,
+		}
+	}
+	pub fn hurtbox(&self) -> Rect{
+		hurtbox(self.position.to_point(), PLAYER_SIZE.0, PLAYER_SIZE.1)
+	}
+	fn use_item(&mut self, item: &Item, stats: &PlayerStats){
+		self.vitals.health = (self.vitals.health + item.heal).min(self.vitals.max_health);
+		self.mana = (self.mana + item.mana).min(stats.casting.max_mana);
+		self.stamina = (self.stamina + item.stamina).min(stats.stamina.max);
+		if item.cure {
+			self.vitals.status = None;
+		}
+	}
+	fn hit(&mut self, hit: &Hit, stats: &PlayerStats){
+		if self.vitals.take_hit(hit) {
+			//getting hit cancels whatever the player was doing, a dash cut short still has to cool down
+			if matches!(self.state, PlayerState::Dashing(_,_)) {
+				self.dash_cooldown = stats.dash.cooldown;
+			}
+			self.state = PlayerState::Standing;
+			self.hitbox_active = false;
+		}
+	}
+}
+
+fn player_hitbox(player: &BattlePlayerContext, attacks: &Attacks) -> Option<Hitbox>{
+	match player.state {
+		PlayerState::Attacking(attack, _) if player.hitbox_active => {
+			let attack = attacks.get(attack);
+			Some(Hitbox{
+				rect: attack_hitbox(player.position.to_point(), player.facing_vector, attack.reach, attack.size),
+				damage: attack.damage,
+				knockback_ticks: attack.knockback_ticks,
+				status: None,
+			})
+		},
+		_ => None
+	}
+}
+
+fn released_spell(player: &BattlePlayerContext, sprites: &SpriteSheet) -> bool{
+	//the casting animation says when the spell leaves the player's hands
+	matches!(player.state, PlayerState::Casting(_,_)) && player.animation.has_event(sprites, "release")
+}
+
+fn aim_angle(input: &InputState) -> Option<f32>{
+	//the right stick aims spells when it's pushed, the axes are already dead zoned
+	if input.right_x_pos != 0 || input.right_y_pos != 0 {
+		Some((-(input.right_y_pos as f32)).atan2(input.right_x_pos as f32))
+	}else{
+		None
+	}
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
 pub enum PlayerState{
 	Standing,
 	Running,
 	Dashing(usize, usize),
-	Attacking(usize, usize),
-	Blocking,
+	Attacking(AttackId, usize), //which attack and how far into it
+	Blocking(usize), //ticks spent blocking, the first few of them parry
 	Casting(usize, usize)
 }
 
-fn update_battle_player(player: &mut BattlePlayerContext, input: &InputState, sound_manager: &mut SoundManager){
+fn player_clip<'a>(state: &PlayerState, attacks: &'a Attacks) -> (&'a str, Option<usize>){
+	//the clip for a state, and the tick to show for states that count their own frames
+	match state{
+		PlayerState::Standing => ("standing", None),
+		PlayerState::Running => ("running", None),
+		PlayerState::Dashing(framecount, _) => ("dashing", Some(*framecount)),
+		PlayerState::Attacking(attack, framecount) => (&attacks.get(*attack).clip, Some(*framecount)),
+		PlayerState::Blocking(_) => ("blocking", None),
+		PlayerState::Casting(framecount, _) => ("casting", Some(*framecount)),
+	}
+}
+
+fn animate_player(player: &mut BattlePlayerContext, sprites: &SpriteSheet, attacks: &Attacks){
+	let (clip_name, framecount) = player_clip(&player.state, attacks);
+	match (sprites.directional_clip(clip_name, player.facing_vector), framecount) {
+		(Some((clip, flipped)), Some(framecount)) => {
+			//an attack's clip is stretched to however long the attack lasts
+			let framecount = match player.state {
+				PlayerState::Attacking(attack, _) => framecount * sprites.clip(clip).total_ticks() / attacks.get(attack).ticks.max(1),
+				_ => framecount,
+			};
+			player.animation.play_at(clip, framecount, flipped)
+		},
+		(Some((clip, flipped)), None) => player.animation.play(clip, flipped),
+		(None, _) => player.animation = AnimationState::new(),
+	}
+}
+
+fn facing_hit(player: &BattlePlayerContext, hit: &Hit, guard: &GuardStats) -> bool{
+	//a block only covers hits from in front, the attacker is on the far side of the knockback
+	let towards_attacker = hit.knockback_vector + std::f32::consts::PI;
+	let difference = (towards_attacker - player.facing_vector + std::f32::consts::PI).rem_euclid(std::f32::consts::TAU) - std::f32::consts::PI;
+	difference.abs() <= guard.block_arc.to_radians() / 2.0
+}
+
+fn wants_to_block(input: &InputState) -> bool{
+	input.left_shoulder || input.left_trigger
+}
+
+fn regenerate(player: &mut BattlePlayerContext, stats: &PlayerStats){
+	if matches!(player.state, PlayerState::Blocking(_)) {
+		player.guard_cooldown = stats.guard.regen_delay;
+	}else if player.guard_cooldown > 0 {
+		player.guard_cooldown -= 1;
+	}else{
+		player.guard = (player.guard + stats.guard.regen).min(stats.guard.max);
+	}
+	player.mana = (player.mana + stats.casting.mana_regen).min(stats.casting.max_mana);
+	player.dash_cooldown = player.dash_cooldown.saturating_sub(1);
+	if matches!(player.state, PlayerState::Attacking(_,_) | PlayerState::Dashing(_,_)) {
+		player.stamina_cooldown = stats.stamina.regen_delay;
+	}else if player.stamina_cooldown > 0 {
+		player.stamina_cooldown -= 1;
+	}else{
+		player.stamina = (player.stamina + stats.stamina.regen).min(stats.stamina.max);
+	}
+}
+
+fn buffer_attack(player: &mut BattlePlayerContext, input: &InputState, attacks: &Attacks){
+	//remembers an attack press for a few ticks, so one made during a swing or a dash still comes out once it can
+	let pressed = if input.just_pressed(InputButton::Down) {
+		Some(AttackInput::Light)
+	}else if input.just_pressed(InputButton::Up) {
+		Some(AttackInput::Heavy)
+	}else{
+		None
+	};
+	if pressed.is_some() {
+		player.buffered_attack = pressed;
+		player.buffer_ticks = attacks.buffer_ticks;
+	}else if player.buffer_ticks > 0 {
+		player.buffer_ticks -= 1;
+	}else{
+		player.buffered_attack = None;
+	}
+}
+
+fn next_attack(player: &BattlePlayerContext, links: &[Link], attacks: &Attacks) -> Option<AttackId>{
+	//the attack the buffered press leads to from links, if there's enough stamina for it
+	let attack = find_link(links, player.buffered_attack?)?;
+	(player.stamina >= attacks.get(attack).cost).then_some(attack)
+}
+
+fn start_attack(player: &mut BattlePlayerContext, attack: AttackId, stats: &PlayerStats, attacks: &Attacks){
+	spend_stamina(player, stats, attacks.get(attack).cost);
+	player.buffered_attack = None;
+	player.hitbox_active = false;
+	player.state = PlayerState::Attacking(attack, 0);
+}
+
+fn spend_stamina(player: &mut BattlePlayerContext, stats: &PlayerStats, cost: f32){
+	player.stamina = (player.stamina - cost).max(0.0);
+	player.stamina_cooldown = stats.stamina.regen_delay;
+}
+
+fn dodging(player: &BattlePlayerContext, stats: &PlayerStats) -> bool{
+	//the middle of a dash passes through hits
+	match player.state {
+		PlayerState::Dashing(framecount, _) => (stats.dash.invulnerable_start..stats.dash.invulnerable_end).contains(&framecount),
+		_ => false
+	}
+}
+
+fn run_velocity(player: &BattlePlayerContext, speed: f32) -> Vec2{
+	//the velocity for moving along facing_vector at speed, slowed down by status effects
+	Vec2::from_angle(player.facing_vector, speed * player.vitals.speed_multiplier())
+}
+
+fn update_battle_player(player: &mut BattlePlayerContext, input: &InputState, sound_manager: &mut SoundManager, data: &GameData, arena: &Arena){
+	let (sprites, stats) = (&data.player_sprites, &data.player_stats);
+	let movement = &stats.movement;
+	let spell = data.spells.get(player.spell);
+	regenerate(player, stats);
+	let knocked_back = player.vitals.tick(&mut player.velocity, &movement.stats);
+	//animate first so the animation is on the same tick as the state's framecount below
+	animate_player(player, sprites, &data.attacks);
+	buffer_attack(player, input, &data.attacks);
+	if knocked_back {
+		//no control while being knocked back
+		arena.move_and_slide(&mut player.position, &mut player.velocity, PLAYER_SIZE);
+		return;
+	}
+	//states that move set where they want to go, everything else comes to a stop
+	let mut target_velocity = Vec2::ZERO;
+	let attacks = &data.attacks;
+	let can_dash = player.dash_cooldown == 0 && player.stamina >= stats.dash.cost;
 	match &player.state{
+		PlayerState::Standing | PlayerState::Running if wants_to_block(input) && player.guard > 0.0 => {
+			player.state = PlayerState::Blocking(0);
+		},
+		PlayerState::Standing | PlayerState::Running if input.just_pressed(InputButton::Left) && player.mana >= spell.mana_cost => {
+			player.mana -= spell.mana_cost;
+			player.state = PlayerState::Casting(0, spell.cast_ticks);
+		},
 		PlayerState::Standing => {
-			match (get_player_intent_vector(input), &input.btn_down, &input.right_shoulder){
-				(_, true, _) => {
-					player.state = PlayerState::Attacking(0,30);
+			match (get_player_intent_vector(input), next_attack(player, &attacks.ground_openers, attacks), &input.right_shoulder){
+				(_, Some(attack), _) => {
+					start_attack(player, attack, stats, attacks);
 				}
-				(Some(x), false, _) => {
-					player.facing_vector = x;
-					const RUNNING_SPEED: f32 = 2.0;
-					player.position.x += (player.facing_vector.cos() * RUNNING_SPEED) as i32;
-					player.position.y -= (player.facing_vector.sin() * RUNNING_SPEED) as i32;
+				(Some(intent), None, _) => {
+					player.facing_vector = intent.direction;
+					target_velocity = run_velocity(player, movement.run_speed * intent.magnitude);
 					player.state = PlayerState::Running;
 				},
-				(None, false, _) => (),
+				(None, None, _) => (),
 			};
 		},
 		PlayerState::Running => {
-			match (get_player_intent_vector(input), &input.btn_down, &input.right_shoulder){
-				(_, true, _) => {
-					player.state = PlayerState::Attacking(0,30);
+			match (get_player_intent_vector(input), next_attack(player, &attacks.ground_openers, attacks), &input.right_shoulder){
+				(_, Some(attack), _) => {
+					start_attack(player, attack, stats, attacks);
 				},
-				(Some(x), false, true) => {
-					//set angle then start dashing
-					player.facing_vector = x;
-					player.state = PlayerState::Dashing(0, 30);
+				(Some(intent), None, true) if can_dash => {
+					//set angle then start dashing, dashes are always full speed
+					player.facing_vector = intent.direction;
+					spend_stamina(player, stats, stats.dash.cost);
+					player.state = PlayerState::Dashing(0, stats.dash.ticks);
 				},
-				(Some(x), false, false) => {
-					//still running
-					player.facing_vector = x;
-					const RUNNING_SPEED: f32 = 2.0;
-					player.position.x += (player.facing_vector.cos() * RUNNING_SPEED) as i32;
-					player.position.y -= (player.facing_vector.sin() * RUNNING_SPEED) as i32;
+				(Some(intent), None, _) => {
+					//still running, as fast as the stick is pushed
+					player.facing_vector = intent.direction;
+					target_velocity = run_velocity(player, movement.run_speed * intent.magnitude);
 				},
-				(None, false, _) => {
+				(None, None, _) => {
 					player.state = PlayerState::Standing;
 				},
 			};
 		},
 		PlayerState::Dashing(framecount, max_frames) => {
 			if *framecount == 0 {
-				let file = BufReader::new(File::open("assets/sounds/chicken.ogg").unwrap());
-				let source = Decoder::new(file).unwrap();
-				sound_manager.play("chicken", source);
+				if let Err(why) = sound_manager.play_sfx("chicken") {
+					println!("{}", why);
+				}
 			}
-			if framecount == max_frames{
+			if let Some(attack) = next_attack(player, &attacks.dash_openers, attacks) {
+				//dash attacks cut the dash short, and still count as having dashed for the cooldown
+				player.dash_cooldown = stats.dash.cooldown;
+				start_attack(player, attack, stats, attacks);
+			}else if framecount == max_frames{
+				player.dash_cooldown = stats.dash.cooldown;
 				player.state = PlayerState::Standing;
 			}else{
-				const DASHING_SPEED: f32 = 5.0;
-				player.position.x += (player.facing_vector.cos() * DASHING_SPEED) as i32;
-				player.position.y -= (player.facing_vector.sin() * DASHING_SPEED) as i32;
+				//dashes go straight to full speed, they only ease out once they're over
 				player.state = PlayerState::Dashing(framecount+1, *max_frames);
+				player.velocity = run_velocity(player, movement.dash_speed);
+				target_velocity = player.velocity;
 			}
 		},
-		PlayerState::Attacking(framecount, max_frames) => {
-			if *framecount == 0{
-				let file = BufReader::new(File::open("assets/sounds/sword_swing.ogg").unwrap());
-				let source = Decoder::new(file).unwrap();
-				sound_manager.play("swordswing", source);
-
-			}
-			if *framecount == 6{
-				();
+		PlayerState::Attacking(attack_id, framecount) => {
+			let (attack_id, framecount) = (*attack_id, *framecount);
+			let attack = attacks.get(attack_id);
+			if framecount == 0{
+				//heavier attacks swing louder
+				let volume = (attack.damage as f32 / 20.0).clamp(0.4, 1.0);
+				if let Err(why) = sound_manager.play_sfx_at_volume("sword_swing", volume) {
+					println!("{}", why);
+				}
 			}
-			if *framecount == 24{
-				();
+			player.hitbox_active = attack.hits(framecount);
+			let chained = match attack.can_cancel(framecount) {
+				true => next_attack(player, &attack.next, attacks),
+				false => None,
+			};
+			if let Some(next) = chained {
+				start_attack(player, next, stats, attacks);
+			}else if framecount >= attack.ticks{
+				player.hitbox_active = false;
+				player.state = PlayerState::Standing;
+			}else{
+				player.state = PlayerState::Attacking(attack_id, framecount+1);
+				if attack.lunge > 0.0 {
+					target_velocity = run_velocity(player, attack.lunge);
+				}
 			}
+		},
+		PlayerState::Casting(framecount, max_frames) => {
+			//rooted in place until the cast is over, the spell itself comes out in update_live
 			if framecount == max_frames{
 				player.state = PlayerState::Standing;
 			}else{
-				player.state = PlayerState::Attacking(framecount+1, *max_frames);
+				player.state = PlayerState::Casting(framecount+1, *max_frames);
 			}
 		},
-		PlayerState::Casting(framecount, max_frames) => (),
-		PlayerState::Blocking => ()
+		PlayerState::Blocking(ticks) => {
+			if !wants_to_block(input) || player.guard <= 0.0 {
+				player.state = PlayerState::Standing;
+			}else{
+				//can turn to face an attack but not move
+				if let Some(intent) = get_player_intent_vector(input) {
+					player.facing_vector = intent.direction;
+				}
+				player.state = PlayerState::Blocking(ticks+1);
+			}
+		}
 	}
+	player.velocity = movement.stats.steer(player.velocity, target_velocity);
+	arena.move_and_slide(&mut player.position, &mut player.velocity, PLAYER_SIZE);
 }
 
-pub fn render_battle(canvas: &mut WindowCanvas, background_texture: &Texture, ctx: &BattleContext){
+pub fn render_battle(canvas: &mut WindowCanvas, assets: &mut AssetManager, data: &GameData, ctx: &BattleContext, previous: Option<&BattleContext>, alpha: f32){
 	let player = ctx.player;
+	canvas.set_draw_color(Color::RGB(0, 0, 0)); //letterbox bars
 	canvas.clear();
-	let (width, height) = canvas.output_size().unwrap();
+	let (width, height) = canvas.logical_size();
+	let background_texture = assets.get_texture(assets.texture("background").expect("Background texture not loaded"));
 	canvas.copy(background_texture, None, None).expect("Couldn't draw background texture.");
-	let player_rect = Rect::from_center(player.position, 50, 50);
-	let player_color = match player.state{
-		PlayerState::Standing => Color::RGB(0,255,0),
-		PlayerState::Running => Color::RGB(255, 255, 0),
-		PlayerState::Dashing(_,_) => Color::RGB(255,165,0),
-		PlayerState::Attacking(_,_) => Color::RGB(255,0,0),
-		PlayerState::Casting(_,_) => Color::RGB(255,0,255),
-		PlayerState::Blocking => Color::RGB(40,40,40),
+	draw_arena(canvas, data.arenas.get(ctx.arena));
+	//draw the player between its last two simulated positions so movement stays smooth between ticks
+	let player_position = match previous {
+		Some(previous) => previous.player.position.lerp(player.position, alpha).to_point(),
+		None => player.position.to_point()
 	};
-	canvas.set_draw_color(player_color);
-	canvas.fill_rect(player_rect).unwrap();
+	for (i, enemy) in ctx.enemies.iter().enumerate() {
+		if let Some(enemy) = enemy {
+			let previous_enemy = previous.and_then(|previous| previous.enemies[i]);
+			let enemy_position = match previous_enemy {
+				Some(previous_enemy) => previous_enemy.position.lerp(enemy.position, alpha).to_point(),
+				None => enemy.position.to_point()
+			};
+			draw_enemy(canvas, data, enemy, enemy_position);
+		}
+	}
+	for (i, projectile) in ctx.projectiles.iter().enumerate() {
+		if let Some(projectile) = projectile {
+			let previous_projectile = previous.and_then(|previous| previous.projectiles[i]);
+			draw_projectile(canvas, &data.spells, projectile, previous_projectile.as_ref(), alpha);
+		}
+	}
+	//flicker while invulnerable after a hit
+	if !blinking(&player.vitals) && !draw_player_sprite(canvas, assets, &data.player_sprites, &player, player_position) {
+		draw_player_placeholder(canvas, &player, player_position);
+	}
+	draw_health_bar(canvas, Rect::new(20, 20, 300, 16), &player.vitals);
+	draw_bar(canvas, Rect::new(20, 40, 300, 8), player.guard / data.player_stats.guard.max, Color::RGB(200,200,200));
+	draw_bar(canvas, Rect::new(20, 52, 300, 8), player.mana / data.player_stats.casting.max_mana, Color::RGB(60,120,255));
+	draw_bar(canvas, Rect::new(20, 64, 300, 8), player.stamina / data.player_stats.stamina.max, Color::RGB(80,200,80));
 
 	match ctx.state{
-		BattleState::Paused =>{
+		BattleState::Paused(menu) =>{
+			render_pause_menu(canvas, assets, data, &menu, ctx);
+		},
+		BattleState::Finished(outcome) =>{
 			canvas.set_blend_mode(BlendMode::Blend);
-			canvas.set_draw_color((0,0,255,128));
+			canvas.set_draw_color((0,0,0,160));
 			canvas.fill_rect(Rect::new(0, 0, width, height)).expect("Failed to draw a rectangle");
 			canvas.set_blend_mode(BlendMode::None);//put the blend mode back to normal
+			let font = assets.font("frontman").expect("Battle font not loaded");
+			let (message, color) = match outcome {
+				BattleOutcome::Victory => ("Victory", Color::RGBA(255, 225, 0, 255)),
+				BattleOutcome::Defeat => ("Defeat", Color::RGBA(255, 40, 40, 255)),
+			};
+			let style = TextStyle::new(font, 128, color)
+				.align(Align::Center)
+				.shadow(Point::new(6, 6), Color::RGBA(0, 0, 0, 160));
+			let message_box = Rect::new(0, (height / 3) as i32, width, height / 4);
+			draw_text(canvas, assets, &style, message, message_box).expect("Couldn't write battle result text.");
 		},
 		_ => ()
 	};
 
 	canvas.present();
-}
\ No newline at end of file
+}
+
+fn draw_player_sprite(canvas: &mut WindowCanvas, assets: &AssetManager, sprites: &SpriteSheet, player: &BattlePlayerContext, position: Point) -> bool{
+	//draws the current animation frame with its pivot on the player's position, false if there's nothing to draw
+	let frame = match player.animation.current_frame(sprites) {
+		Some(frame) => frame,
+		None => return false
+	};
+	let texture = match assets.texture(&sprites.texture) {
+		Some(texture) => assets.get_texture(texture),
+		None => return false
+	};
+	let source = frame.source_rect();
+	let pivot = frame.pivot();
+	//mirroring the frame mirrors its pivot too
+	let pivot_x = if player.animation.flipped { source.width() as i32 - pivot.x } else { pivot.x };
+	let destination = Rect::new(position.x - pivot_x, position.y - pivot.y, source.width(), source.height());
+	canvas.copy_ex(texture, source, destination, 0.0, None, player.animation.flipped, false)
+		.expect("Couldn't draw player sprite.");
+	true
+}
+
+fn blinking(vitals: &Vitals) -> bool{
+	(vitals.invulnerable_ticks / 4) % 2 == 1
+}
+
+fn draw_enemy(canvas: &mut WindowCanvas, data: &GameData, enemy: &Enemy, position: Point){
+	//enemies don't have sprites yet
+	if !blinking(&enemy.vitals) {
+		let color = match enemy.action(&data.enemy_types) {
+			Action::Telegraph if (enemy.state_ticks / 3).is_multiple_of(2) => Color::RGB(255,255,255),
+			Action::Telegraph | Action::Attack{..} => Color::RGB(255,60,60),
+			_ => Color::RGB(150,0,200),
+		};
+		canvas.set_draw_color(color);
+		canvas.fill_rect(Rect::from_center(position, enemy.size.0, enemy.size.1)).unwrap();
+	}
+	let bar_top = position.y - enemy.size.1 as i32 / 2 - 10;
+	draw_health_bar(canvas, Rect::new(position.x - enemy.size.0 as i32 / 2, bar_top, enemy.size.0, 5), &enemy.vitals);
+}
+
+fn draw_arena(canvas: &mut WindowCanvas, arena: &Arena){
+	canvas.set_blend_mode(BlendMode::Blend);
+	canvas.set_draw_color((0,0,0,140));
+	for obstacle in &arena.obstacles {
+		match *obstacle {
+			Obstacle::Rect{ rect } => {
+				canvas.fill_rect(to_rect(rect)).unwrap();
+			},
+			Obstacle::Circle{ center, radius } => {
+				//one line per row, there's no circle drawing in plain SDL
+				let radius = radius as i32;
+				for dy in -radius..=radius {
+					let half_width = ((radius * radius - dy * dy) as f32).sqrt() as i32;
+					let y = center[1] + dy;
+					canvas.draw_line(Point::new(center[0] - half_width, y), Point::new(center[0] + half_width, y)).unwrap();
+				}
+			},
+		}
+	}
+	canvas.set_draw_color((255,255,255,60));
+	canvas.draw_rect(arena.bounds()).unwrap();
+	canvas.set_blend_mode(BlendMode::None);//put the blend mode back to normal
+}
+
+fn draw_projectile(canvas: &mut WindowCanvas, spells: &Spells, projectile: &Projectile, previous: Option<&Projectile>, alpha: f32){
+	let spell = spells.get(projectile.spell);
+	let (x, y) = match previous {
+		//a previous projectile from another spell means the slot was reused, nothing to blend from
+		Some(previous) if previous.spell == projectile.spell => (
+			previous.x + (projectile.x - previous.x) * alpha,
+			previous.y + (projectile.y - previous.y) * alpha
+		),
+		_ => (projectile.x, projectile.y)
+	};
+	let center = Point::new(x.round() as i32, y.round() as i32);
+	let [r, g, b] = spell.color;
+	canvas.set_draw_color(Color::RGB(r, g, b));
+	canvas.fill_rect(Rect::from_center(center, spell.radius * 2, spell.radius * 2)).unwrap();
+	canvas.set_draw_color(Color::RGB(r.saturating_add(100), g.saturating_add(100), b.saturating_add(100)));
+	canvas.fill_rect(Rect::from_center(center, spell.radius, spell.radius)).unwrap();
+}
+
+fn draw_health_bar(canvas: &mut WindowCanvas, bounds: Rect, vitals: &Vitals){
+	let fraction = vitals.health.max(0) as f32 / vitals.max_health.max(1) as f32;
+	draw_bar(canvas, bounds, fraction, Color::RGB(200,30,30));
+}
+
+fn draw_bar(canvas: &mut WindowCanvas, bounds: Rect, fraction: f32, color: Color){
+	canvas.set_draw_color(Color::RGB(40,40,40));
+	canvas.fill_rect(bounds).unwrap();
+	canvas.set_draw_color(color);
+	canvas.fill_rect(Rect::new(bounds.x(), bounds.y(), (bounds.width() as f32 * fraction.clamp(0.0, 1.0)) as u32, bounds.height())).unwrap();
+}
+
+fn draw_player_placeholder(canvas: &mut WindowCanvas, player: &BattlePlayerContext, position: Point){
+	let player_rect = Rect::from_center(position, 50, 50);
+	let player_color = match player.state{
+		PlayerState::Standing => Color::RGB(0,255,0),
+		PlayerState::Running => Color::RGB(255, 255, 0),
+		PlayerState::Dashing(_,_) => Color::RGB(255,165,0),
+		PlayerState::Attacking(_,_) => Color::RGB(255,0,0),
+		PlayerState::Casting(_,_) => Color::RGB(255,0,255),
+		PlayerState::Blocking(_) => Color::RGB(40,40,40),
+	};
+	canvas.set_draw_color(player_color);
+	canvas.fill_rect(player_rect).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::f32::consts::PI;
+	use crate::combat::{Status, StatusEffect};
+
+	fn battle() -> (GameData, BattleContext){
+		let data = GameData::load().unwrap();
+		let battle = BattleContext::new(&data, data.default_arena);
+		(data, battle)
+	}
+
+	fn stunning_hit(knockback_vector: f32) -> Hit{
+		Hit{
+			attacker: Target::Enemy(0),
+			target: Target::Player,
+			damage: 20,
+			knockback_vector,
+			knockback_ticks: 10,
+			status: Some(Status{ effect: StatusEffect::Stun, ticks: 30 }),
+		}
+	}
+
+	fn light_press() -> InputState{
+		let mut input = InputState::new();
+		input.btn_down = true;
+		input.advance_tick(&InputState::new());
+		input
+	}
+
+	#[test]
+	fn a_buffered_press_runs_out(){
+		let (data, mut battle) = battle();
+		buffer_attack(&mut battle.player, &light_press(), &data.attacks);
+		for _ in 0..data.attacks.buffer_ticks {
+			buffer_attack(&mut battle.player, &InputState::new(), &data.attacks);
+			assert_eq!(battle.player.buffered_attack, Some(AttackInput::Light));
+		}
+		buffer_attack(&mut battle.player, &InputState::new(), &data.attacks);
+		assert_eq!(battle.player.buffered_attack, None);
+	}
+
+	#[test]
+	fn a_buffered_press_picks_the_matching_link(){
+		let (data, mut battle) = battle();
+		let attacks = &data.attacks;
+		buffer_attack(&mut battle.player, &light_press(), attacks);
+		let opener = next_attack(&battle.player, &attacks.ground_openers, attacks).unwrap();
+		assert_eq!(Some(opener), find_link(&attacks.ground_openers, AttackInput::Light));
+		//nothing comes out without the stamina for it
+		battle.player.stamina = attacks.get(opener).cost - 1.0;
+		assert_eq!(next_attack(&battle.player, &attacks.ground_openers, attacks), None);
+		battle.player.buffered_attack = None;
+		battle.player.stamina = data.player_stats.stamina.max;
+		assert_eq!(next_attack(&battle.player, &attacks.ground_openers, attacks), None);
+	}
+
+	#[test]
+	fn a_dash_cut_short_by_a_hit_still_cools_down(){
+		let (data, mut battle) = battle();
+		let stats = &data.player_stats;
+		battle.player.state = PlayerState::Dashing(stats.dash.invulnerable_end + 1, stats.dash.ticks);
+		battle.resolve_hits(&[stunning_hit(0.0)], stats);
+		assert!(matches!(battle.player.state, PlayerState::Standing));
+		assert_eq!(battle.player.dash_cooldown, stats.dash.cooldown);
+	}
+
+	#[test]
+	fn blocking_a_hit_from_the_front_only_chips_health(){
+		let (data, mut battle) = battle();
+		let guard = &data.player_stats.guard;
+		//facing right, pushed left means the attacker is in front
+		battle.player.facing_vector = 0.0;
+		battle.player.state = PlayerState::Blocking(guard.parry_window);
+		battle.resolve_hits(&[stunning_hit(PI)], &data.player_stats);
+		let chip = (20.0 * guard.damage_multiplier).round() as i32;
+		assert_eq!(battle.player.vitals.health, data.player_stats.max_health - chip);
+		assert_eq!(battle.player.vitals.knockback_ticks, 0);
+		assert!(battle.player.vitals.status.is_none());
+		assert!(matches!(battle.player.state, PlayerState::Blocking(_)));
+	}
+
+	#[test]
+	fn blocking_does_not_cover_hits_from_behind(){
+		let (data, mut battle) = battle();
+		battle.player.facing_vector = 0.0;
+		battle.player.state = PlayerState::Blocking(data.player_stats.guard.parry_window);
+		battle.resolve_hits(&[stunning_hit(0.0)], &data.player_stats);
+		assert_eq!(battle.player.vitals.health, data.player_stats.max_health - 20);
+		assert_eq!(battle.player.vitals.knockback_ticks, 10);
+		assert!(battle.player.vitals.is_stunned());
+		assert!(matches!(battle.player.state, PlayerState::Standing));
+	}
+}
@@ -1,2 +1,3 @@
 pub mod battle;
+pub mod pause_menu;
 pub mod start;
\ No newline at end of file
@@ -0,0 +1,282 @@
+use sdl2::pixels::Color;
+use sdl2::rect::{Point, Rect};
+use sdl2::render::{WindowCanvas, BlendMode};
+use crate::assets::AssetManager;
+use crate::game_data::GameData;
+use crate::input::{InputState, InputButton};
+use crate::items::ItemId;
+use crate::screens::battle::BattleContext;
+use crate::sound_manager::{Bus, BusSettings, SoundManager};
+use crate::spells::SpellId;
+use crate::text::{draw_text, measure_text, Align, TextStyle};
+
+// The menu a battle shows while it's paused. It only ever runs while the battle is frozen,
+// anything it does to the battle is handed back as a MenuAction for the battle to apply.
+
+const REPEAT_DELAY: usize = 20; //ticks a direction has to be held before the cursor starts repeating
+const REPEAT_RATE: usize = 6;
+const STICK_THRESHOLD: i16 = 16000; //how far the stick has to be pushed to move the cursor
+const VOLUME_STEP: f32 = 0.1;
+const RETREAT_HOLD_TICKS: u32 = 45; //confirm has to be held this long to retreat, so it can't be picked by accident
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MenuPage{
+	Main,
+	Items,
+	Skills,
+	Options,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum MainEntry{
+	Resume,
+	Items,
+	Skills,
+	Options,
+	Retreat,
+}
+
+const MAIN_ENTRIES: [(&str, MainEntry); 5] = [
+	("Resume", MainEntry::Resume),
+	("Items", MainEntry::Items),
+	("Skills", MainEntry::Skills),
+	("Options", MainEntry::Options),
+	("Retreat to title", MainEntry::Retreat),
+];
+
+const OPTION_BUSES: [(&str, Bus); 2] = [
+	("Music", Bus::Music),
+	("Sound effects", Bus::Sfx),
+];
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Nav{
+	Up,
+	Down,
+	Left,
+	Right,
+}
+
+// What the battle has to do after a tick of the menu
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MenuAction{
+	Stay,
+	Resume,
+	UseItem(ItemId),
+	Equip(SpellId),
+	Retreat,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PauseMenu{
+	pub page: MenuPage,
+	pub cursor: usize,
+	held: Option<Nav>, //the direction held last tick, for repeating
+	held_ticks: usize,
+	retreat_hold: u32, //how long confirm has been held on retreat, for the progress bar
+	volumes: [BusSettings; 2], //what the options page shows, read from the sound manager when it's opened
+}
+
+impl PauseMenu{
+	pub fn new(input: &InputState) -> PauseMenu{
+		PauseMenu{
+			page: MenuPage::Main,
+			cursor: 0,
+			//a direction already held when pausing shouldn't move the cursor straight away
+			held: held_nav(input),
+			held_ticks: 0,
+			retreat_hold: 0,
+			volumes: [BusSettings{ volume: 1.0, muted: false }; 2],
+		}
+	}
+
+	pub fn update(&mut self, input: &InputState, data: &GameData, sound_manager: &mut SoundManager) -> MenuAction{
+		let entries = self.entry_count(data);
+		match self.step_nav(input) {
+			Some(Nav::Up) if entries > 0 => self.cursor = (self.cursor + entries - 1) % entries,
+			Some(Nav::Down) if entries > 0 => self.cursor = (self.cursor + 1) % entries,
+			Some(Nav::Left) if self.page == MenuPage::Options => self.change_volume(sound_manager, -VOLUME_STEP),
+			Some(Nav::Right) if self.page == MenuPage::Options => self.change_volume(sound_manager, VOLUME_STEP),
+			_ => (),
+		}
+		if input.just_pressed(InputButton::Right) {
+			//cancel backs out to the main page, and out of the menu from there
+			return match self.page {
+				MenuPage::Main => MenuAction::Resume,
+				page => {
+					let entry = match page {
+						MenuPage::Items => MainEntry::Items,
+						MenuPage::Skills => MainEntry::Skills,
+						_ => MainEntry::Options,
+					};
+					self.page = MenuPage::Main;
+					self.cursor = MAIN_ENTRIES.iter().position(|(_, main_entry)| *main_entry == entry).unwrap_or(0);
+					MenuAction::Stay
+				}
+			};
+		}
+		//only counts a hold that started on retreat, moving onto it with confirm already held doesn't
+		let on_retreat = self.page == MenuPage::Main && MAIN_ENTRIES[self.cursor].1 == MainEntry::Retreat;
+		self.retreat_hold = match (on_retreat, input.held_ticks(InputButton::Down)) {
+			(true, held) if held == self.retreat_hold + 1 => held,
+			_ => 0,
+		};
+		if self.retreat_hold >= RETREAT_HOLD_TICKS {
+			return MenuAction::Retreat;
+		}
+		if !input.just_pressed(InputButton::Down) {
+			return MenuAction::Stay;
+		}
+		match self.page {
+			MenuPage::Main => match MAIN_ENTRIES[self.cursor].1 {
+				MainEntry::Resume => MenuAction::Resume,
+				MainEntry::Items => self.open(MenuPage::Items),
+				MainEntry::Skills => self.open(MenuPage::Skills),
+				MainEntry::Options => {
+					for (settings, (_, bus)) in self.volumes.iter_mut().zip(OPTION_BUSES) {
+						*settings = sound_manager.bus(bus);
+					}
+					self.open(MenuPage::Options)
+				},
+				MainEntry::Retreat => MenuAction::Stay,
+			},
+			MenuPage::Items => data.items.ids().nth(self.cursor).map_or(MenuAction::Stay, MenuAction::UseItem),
+			MenuPage::Skills => data.known_spells.get(self.cursor).copied().map_or(MenuAction::Stay, MenuAction::Equip),
+			MenuPage::Options => {
+				//confirm mutes and unmutes
+				let (settings, (_, bus)) = (&mut self.volumes[self.cursor], OPTION_BUSES[self.cursor]);
+				settings.muted = !settings.muted;
+				sound_manager.set_bus_muted(bus, settings.muted);
+				MenuAction::Stay
+			},
+		}
+	}
+
+	fn open(&mut self, page: MenuPage) -> MenuAction{
+		self.page = page;
+		self.cursor = 0;
+		MenuAction::Stay
+	}
+
+	fn entry_count(&self, data: &GameData) -> usize{
+		match self.page {
+			MenuPage::Main => MAIN_ENTRIES.len(),
+			MenuPage::Items => data.items.ids().count(),
+			MenuPage::Skills => data.known_spells.len(),
+			MenuPage::Options => OPTION_BUSES.len(),
+		}
+	}
+
+	fn change_volume(&mut self, sound_manager: &mut SoundManager, change: f32){
+		let (settings, (_, bus)) = (&mut self.volumes[self.cursor], OPTION_BUSES[self.cursor]);
+		settings.volume = ((settings.volume + change) * 10.0).round().clamp(0.0, 10.0) / 10.0;
+		sound_manager.set_bus_volume(bus, settings.volume);
+	}
+
+	fn step_nav(&mut self, input: &InputState) -> Option<Nav>{
+		//a new direction moves once, holding it moves again after a short wait and then keeps repeating
+		let nav = held_nav(input);
+		if nav != self.held {
+			self.held = nav;
+			self.held_ticks = 0;
+			return nav;
+		}
+		self.held_ticks += 1;
+		match self.held_ticks >= REPEAT_DELAY && (self.held_ticks - REPEAT_DELAY).is_multiple_of(REPEAT_RATE) {
+			true => nav,
+			false => None,
+		}
+	}
+}
+
+fn held_nav(input: &InputState) -> Option<Nav>{
+	//the dpad, or the left stick pushed most of the way, up is negative y on the stick
+	if input.dpad_up || input.left_y_pos < -STICK_THRESHOLD {
+		Some(Nav::Up)
+	}else if input.dpad_down || input.left_y_pos > STICK_THRESHOLD {
+		Some(Nav::Down)
+	}else if input.dpad_left || input.left_x_pos < -STICK_THRESHOLD {
+		Some(Nav::Left)
+	}else if input.dpad_right || input.left_x_pos > STICK_THRESHOLD {
+		Some(Nav::Right)
+	}else{
+		None
+	}
+}
+
+pub fn render_pause_menu(canvas: &mut WindowCanvas, assets: &mut AssetManager, data: &GameData, menu: &PauseMenu, ctx: &BattleContext){
+	let (width, height) = canvas.logical_size();
+	canvas.set_blend_mode(BlendMode::Blend);
+	canvas.set_draw_color((0,0,60,140));
+	canvas.fill_rect(Rect::new(0, 0, width, height)).expect("Failed to draw a rectangle");
+	let panel = Rect::from_center(Point::new(width as i32 / 2, height as i32 / 2), 560, 440);
+	canvas.set_draw_color((0,0,0,200));
+	canvas.fill_rect(panel).expect("Failed to draw a rectangle");
+	canvas.set_blend_mode(BlendMode::None);//put the blend mode back to normal
+	canvas.set_draw_color(Color::RGB(255, 225, 0));
+	canvas.draw_rect(panel).expect("Failed to draw a rectangle");
+
+	//each entry is its label and, for the pages that have one, a value shown on the right
+	let (title, entries): (&str, Vec<(String, String, bool)>) = match menu.page {
+		MenuPage::Main => ("Paused", MAIN_ENTRIES.iter().map(|(label, _)| (label.to_string(), String::new(), true)).collect()),
+		MenuPage::Items => ("Items", data.items.ids().map(|id| {
+			let count = ctx.items.count(id);
+			(data.items.get(id).name.clone(), format!("x{}", count), count > 0)
+		}).collect()),
+		MenuPage::Skills => ("Skills", data.known_spells.iter().map(|&id| {
+			let cost = data.spells.get(id).mana_cost;
+			let value = if id == ctx.player.spell { format!("equipped  {} mp", cost) } else { format!("{} mp", cost) };
+			(data.spells.name(id).replace('_', " "), value, true)
+		}).collect()),
+		MenuPage::Options => ("Options", OPTION_BUSES.iter().zip(menu.volumes).map(|((label, _), settings)| {
+			let value = if settings.muted { "muted".to_string() } else { format!("< {} >", (settings.volume * 10.0).round()) };
+			(label.to_string(), value, true)
+		}).collect()),
+	};
+
+	let font = assets.font("frontman").expect("Battle font not loaded");
+	let title_style = TextStyle::new(font, 64, Color::RGBA(255, 225, 0, 255))
+		.align(Align::Center)
+		.shadow(Point::new(4, 4), Color::RGBA(0, 0, 0, 160));
+	let title_box = Rect::new(panel.x(), panel.y() + 16, panel.width(), 64);
+	draw_text(canvas, assets, &title_style, title, title_box).expect("Couldn't write pause menu text.");
+	if entries.is_empty() {
+		let style = TextStyle::new(font, 36, Color::RGBA(140, 140, 140, 255)).align(Align::Center);
+		let empty_box = Rect::new(panel.x(), panel.y() + 120, panel.width(), 48);
+		draw_text(canvas, assets, &style, "Nothing here", empty_box).expect("Couldn't write pause menu text.");
+	}
+	for (i, (label, value, available)) in entries.iter().enumerate() {
+		let color = match (i == menu.cursor, available) {
+			(true, _) => Color::RGBA(255, 64, 0, 255),
+			(false, true) => Color::RGBA(255, 255, 255, 255),
+			(false, false) => Color::RGBA(140, 140, 140, 255),
+		};
+		let row = Rect::new(panel.x() + 40, panel.y() + 110 + i as i32 * 56, panel.width() - 80, 48);
+		let label = if i == menu.cursor { format!("> {}", label) } else { label.clone() };
+		draw_text(canvas, assets, &TextStyle::new(font, 36, color), &label, row).expect("Couldn't write pause menu text.");
+		if !value.is_empty() {
+			let style = TextStyle::new(font, 28, color).align(Align::Right);
+			draw_text(canvas, assets, &style, value, row).expect("Couldn't write pause menu text.");
+		}
+	}
+	//the picked item's description sits at the bottom of the panel, growing upwards as it wraps onto more lines
+	let description = match menu.page {
+		MenuPage::Items => data.items.ids().nth(menu.cursor).map(|id| data.items.get(id).description.as_str()),
+		_ => None,
+	};
+	if let Some(description) = description.filter(|description| !description.is_empty()) {
+		let style = TextStyle::new(font, 24, Color::RGBA(200, 200, 200, 255)).wrap().line_spacing(0.9);
+		let width = panel.width() - 80;
+		let (_, text_height) = measure_text(assets, &style, description, Some(width)).expect("Couldn't measure pause menu text.");
+		let text_height = text_height.min(96);
+		let description_box = Rect::new(panel.x() + 40, panel.bottom() - 16 - text_height as i32, width, text_height);
+		draw_text(canvas, assets, &style, description, description_box).expect("Couldn't write pause menu text.");
+	}
+	if menu.retreat_hold > 0 {
+		//fills under the retreat entry while confirm is held
+		let row_y = panel.y() + 110 + menu.cursor as i32 * 56 + 46;
+		let filled = (panel.width() - 80) * menu.retreat_hold / RETREAT_HOLD_TICKS;
+		canvas.set_draw_color(Color::RGB(255, 64, 0));
+		canvas.fill_rect(Rect::new(panel.x() + 40, row_y, filled.max(1), 4)).expect("Failed to draw a rectangle");
+	}
+}
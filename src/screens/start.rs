@@ -1,20 +1,22 @@
 use sdl2::pixels::Color;
 use sdl2::rect::{Point, Rect};
-use sdl2::render::{WindowCanvas, Texture, BlendMode};
-use sdl2::ttf::FontStyle;
+use sdl2::render::{WindowCanvas, BlendMode};
 use crate::game_context::{GameContext, GameObject};
-use crate::input::{InputState};
+use crate::game_data::GameData;
+use crate::input::{InputState, InputButton};
 use crate::screens::battle::BattleContext;
 use crate::sound_manager::SoundManager;
+use crate::assets::AssetManager;
+use crate::text::{draw_text, Align, TextStyle};
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
 pub enum StartScreenState{
 	FadeIn(usize, usize),
 	Waiting,
 	FadeOut(usize, usize)
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
 pub struct StartScreenContext{
 	pub state: StartScreenState
 }
@@ -24,7 +26,7 @@ impl StartScreenContext{
 			state: StartScreenState::FadeIn(0,30)
 		}
 	}
-	pub fn handle_tick(game_obj: &mut GameObject, input_state: &InputState, sound_manager: &mut SoundManager){
+	pub fn handle_tick(game_obj: &mut GameObject, input_state: &InputState, _sound_manager: &mut SoundManager, data: &GameData){
 		match game_obj.phase {
 			GameContext::StartScreen(start_screen_context) => {
 				match start_screen_context.state {
@@ -35,14 +37,12 @@ impl StartScreenContext{
 					StartScreenState::FadeIn(a,b) => {
 						game_obj.phase = GameContext::StartScreen(StartScreenContext{state: StartScreenState::FadeIn(a+1, b)})
 					},
-					StartScreenState::Waiting => match input_state.btn_start{
-						true => {
-							game_obj.phase = GameContext::StartScreen(StartScreenContext{state: StartScreenState::FadeOut(0,30)})
-						},
-						false => (),
+					//moves on once start is let go, so holding it down doesn't do anything yet
+					StartScreenState::Waiting => if input_state.just_released(InputButton::Start) {
+						game_obj.phase = GameContext::StartScreen(StartScreenContext{state: StartScreenState::FadeOut(0,30)})
 					},
 					StartScreenState::FadeOut(a,b) if a==b => {
-						game_obj.phase = GameContext::Battle(BattleContext::from_game_object(game_obj))
+						game_obj.phase = GameContext::Battle(Box::new(BattleContext::from_game_object(game_obj, data)))
 					},
 					StartScreenState::FadeOut(a, b) if a > b => unreachable!("Frame count above maximum"),
 					StartScreenState::FadeOut(a, b) => {
@@ -55,49 +55,31 @@ impl StartScreenContext{
 	}
 }
 
-pub fn render_start_screen(canvas: &mut WindowCanvas, background_texture: &Texture, context: &StartScreenContext){
-	let (width, height) = canvas.output_size().unwrap();
+pub fn render_start_screen(canvas: &mut WindowCanvas, assets: &mut AssetManager, context: &StartScreenContext){
+	let (width, height) = canvas.logical_size();
+	canvas.set_draw_color(Color::RGB(0, 0, 0)); //letterbox bars
 	canvas.clear();
+	let background_texture = assets.get_texture(assets.texture("background").expect("Background texture not loaded"));
 	canvas.copy(background_texture, None, None).expect("Couldn't draw background texture.");
 
-	let ttf_context = sdl2::ttf::init().unwrap();
-	let mut font = ttf_context.load_font("assets/fonts/The_Frontman.ttf", 128).unwrap();
+	let font = assets.font("frontman").expect("Start screen font not loaded");
+	let title_style = TextStyle::new(font, 128, Color::RGBA(255, 225, 0, 255))
+		.align(Align::Center)
+		.shadow(Point::new(6, 6), Color::RGBA(0, 0, 0, 160));
+	let subtitle_style = TextStyle::new(font, 64, Color::RGBA(255, 225, 0, 255))
+		.align(Align::Center)
+		.shadow(Point::new(4, 4), Color::RGBA(0, 0, 0, 160));
+	let prompt_style = TextStyle::new(font, 48, Color::RGBA(255, 64, 0, 255))
+		.align(Align::Center)
+		.outline(2, Color::RGBA(0, 0, 0, 255));
 
-	// Render the text into a surface.
-	let surface = font.render("Neon Nocturne")
-		.blended(Color::RGBA(255, 225, 0, 255))
-		.unwrap();
-	// Convert the surface to a texture.
-	let texture_creator = canvas.texture_creator();
-	let texture = texture_creator.create_texture_from_surface(&surface).unwrap();
-	// Draw the texture on the canvas.
-	let texture_query = texture.query();
-	let target_rect = Rect::new(50, 100, (width as f32 *0.8) as u32, (height as f32 *0.25) as u32);
-	canvas.copy(&texture, None, Some(target_rect)).expect("Couldn't write start screen text.");
-
-	// Render the text into a surface.
-	let surface = font.render("Resonance of Desire")
-		.blended(Color::RGBA(255, 225, 0, 255))
-		.unwrap();
-	// Convert the surface to a texture.
-	let texture_creator = canvas.texture_creator();
-	let texture = texture_creator.create_texture_from_surface(&surface).unwrap();
-	// Draw the texture on the canvas.
-	let texture_query = texture.query();
-	let target_rect = Rect::new(50, 250, 400, 100);
-	canvas.copy(&texture, None, Some(target_rect)).expect("Couldn't write start screen text.");
-
-	// Render the text into a surface.
-	let surface = font.render("Press Start")
-		.blended(Color::RGBA(255, 64, 0, 255))  // Black text
-		.unwrap();
-	// Convert the surface to a texture.
-	let texture_creator = canvas.texture_creator();
-	let texture = texture_creator.create_texture_from_surface(&surface).unwrap();
-	// Draw the texture on the canvas.
-	let texture_query = texture.query();
-	let target_rect = Rect::new(((width / 2)-100) as i32, ((height/2)+50) as i32, 200, 100);
-	canvas.copy(&texture, None, Some(target_rect)).expect("Couldn't write start screen text.");
+	let margin = width / 10;
+	let title_box = Rect::new(margin as i32, (height / 8) as i32, width - margin * 2, height / 4);
+	let subtitle_box = Rect::new(margin as i32, title_box.bottom(), width - margin * 2, height / 8);
+	let prompt_box = Rect::new(margin as i32, ((height / 2) + 50) as i32, width - margin * 2, height / 8);
+	draw_text(canvas, assets, &title_style, "Neon Nocturne", title_box).expect("Couldn't write start screen text.");
+	draw_text(canvas, assets, &subtitle_style, "Resonance of Desire", subtitle_box).expect("Couldn't write start screen text.");
+	draw_text(canvas, assets, &prompt_style, "Press Start", prompt_box).expect("Couldn't write start screen text.");
 
 	match context.state{
 		StartScreenState::Waiting => (),
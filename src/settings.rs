@@ -0,0 +1,49 @@
+use std::fs;
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use crate::input::StickResponse;
+use crate::video::VideoSettings;
+
+// Player settings that persist between runs, saved as toml next to the bindings in the user config directory.
+// Anything missing from the file falls back to its default, so old files keep working as settings are added.
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Settings{
+	#[serde(default)]
+	pub video: VideoSettings,
+	#[serde(default)]
+	pub stick: StickResponse,
+}
+
+impl Settings{
+	pub fn config_path() -> Option<PathBuf>{
+		dirs::config_dir().map(|dir| dir.join("game2").join("settings.toml"))
+	}
+
+	pub fn load_or_default() -> Settings{
+		//missing or broken settings shouldn't stop the game from starting, just use the defaults
+		let path = match Settings::config_path() {
+			Some(path) if path.exists() => path,
+			_ => return Settings::default(),
+		};
+		let loaded = fs::read_to_string(&path)
+			.map_err(|why| why.to_string())
+			.and_then(|text| toml::from_str(&text).map_err(|why| why.to_string()));
+		match loaded {
+			Ok(settings) => settings,
+			Err(why) => {
+				println!("Unable to load settings {}: {}, using defaults", path.display(), why);
+				Settings::default()
+			}
+		}
+	}
+
+	pub fn save(&self) -> Result<(), String>{
+		let path = Settings::config_path().ok_or("Unable to find the user config directory")?;
+		if let Some(dir) = path.parent() {
+			fs::create_dir_all(dir).map_err(|why| format!("Unable to create {}: {}", dir.display(), why))?;
+		}
+		let text = toml::to_string_pretty(self).map_err(|why| format!("Unable to serialize settings: {}", why))?;
+		fs::write(&path, text).map_err(|why| format!("Unable to write settings {}: {}", path.display(), why))
+	}
+}
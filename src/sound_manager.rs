@@ -1,65 +1,329 @@
-use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink};
+use rodio::{Decoder, OutputStream, OutputStreamHandle, Sample, Sink, Source};
+use rodio::cpal::FromSample;
+use rodio::source::Buffered;
 use std::collections::HashMap;
 use std::io::BufReader;
 use std::fs::File;
 
+// A fully decoded sound kept in memory, cloning it is cheap and shares the decoded samples
+pub type SoundSample = Buffered<Decoder<BufReader<File>>>;
+
+// Short sounds that get played during gameplay, decoded up front so playing them never touches the disk
+// Each has its own voice limit, swings play at different volumes so a new one cuts off the quietest
+pub const SOUND_EFFECTS: [(&str, &str, VoiceLimit); 2] = [
+	("chicken", "assets/sounds/chicken.ogg", VoiceLimit{ max_voices: 2, steal: StealPolicy::Oldest }),
+	("sword_swing", "assets/sounds/sword_swing.ogg", VoiceLimit{ max_voices: 3, steal: StealPolicy::Quietest }),
+];
+
+// Every sound plays on a bus, the bus volume and mute apply on top of the sound's own volume
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Bus {
+	Music,
+	Sfx,
+	Voice,
+	Ui,
+}
+
+impl Bus {
+	pub const ALL: [Bus; 4] = [Bus::Music, Bus::Sfx, Bus::Voice, Bus::Ui];
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct BusSettings {
+	pub volume: f32,
+	pub muted: bool,
+}
+
+// Which voice gets cut off when a sound is already playing as many times as it's allowed to
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum StealPolicy {
+	Oldest,
+	Quietest,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct VoiceLimit {
+	pub max_voices: usize,
+	pub steal: StealPolicy,
+}
+
+impl VoiceLimit {
+	pub fn for_bus(bus: Bus) -> VoiceLimit {
+		//sound effects overlap, everything else restarts when it's played again
+		match bus {
+			Bus::Sfx => VoiceLimit{ max_voices: 8, steal: StealPolicy::Oldest },
+			_ => VoiceLimit{ max_voices: 1, steal: StealPolicy::Oldest },
+		}
+	}
+}
+
+struct Voice {
+	sink: Sink,
+	volume: f32,
+	started: u64,
+}
+
+struct PlayingSound {
+	voices: Vec<Voice>,
+	bus: Bus,
+	volume: f32,
+}
+
 pub struct SoundManager {
-	playing_sounds: HashMap<String, Sink>,
-	stream_handles: HashMap<String, (OutputStream, OutputStreamHandle)>
+	playing_sounds: HashMap<String, PlayingSound>,
+	samples: HashMap<String, SoundSample>, //decoded sounds by asset id
+	buses: HashMap<Bus, BusSettings>,
+	voice_limits: HashMap<String, VoiceLimit>, //overrides for VoiceLimit::for_bus by sound name
+	voices_started: u64,
+	//one stream shared by every sink, None when there's no audio device or we're headless
+	output: Option<(OutputStream, OutputStreamHandle)>,
 }
 
 impl SoundManager {
 	pub fn new() -> SoundManager {
+		//falls back to headless behavior if there's no audio device instead of taking the game down
+		let output = match OutputStream::try_default() {
+			Ok(output) => Some(output),
+			Err(why) => {
+				println!("Unable to open audio output, sound is disabled: {}", why);
+				None
+			}
+		};
+		SoundManager::with_output(output)
+	}
+
+	pub fn headless() -> SoundManager {
+		//a sound manager that never opens an audio device
+		//voices still get idle sinks so volumes, buses and queues are tracked the same way, they just never make a sound
+		SoundManager::with_output(None)
+	}
+
+	fn with_output(output: Option<(OutputStream, OutputStreamHandle)>) -> SoundManager {
 		SoundManager {
 			playing_sounds: HashMap::new(),
-			stream_handles: HashMap::new(),
+			samples: HashMap::new(),
+			buses: Bus::ALL.iter()
+				.map(|bus| (*bus, BusSettings{ volume: 1.0, muted: false }))
+				.collect(),
+			voice_limits: HashMap::new(),
+			voices_started: 0,
+			output,
+		}
+	}
+
+	pub fn has_output(&self) -> bool {
+		self.output.is_some()
+	}
+
+	pub fn load(&mut self, id: &str, path: &str) -> Result<(), String> {
+		let file = File::open(path).map_err(|why| format!("Unable to open sound {}: {}", path, why))?;
+		let decoder = Decoder::new(BufReader::new(file)).map_err(|why| format!("Unable to decode sound {}: {}", path, why))?;
+		let sample = decoder.buffered();
+		//run through a copy once so the whole thing is decoded now instead of the first time it plays
+		sample.clone().for_each(drop);
+		self.samples.insert(id.to_string(), sample);
+		Ok(())
+	}
+
+	pub fn preload_sound_effects(&mut self) -> Result<(), String> {
+		for (id, path, limit) in SOUND_EFFECTS {
+			self.load(id, path)?;
+			self.set_voice_limit(id, limit);
 		}
+		Ok(())
 	}
 
-	pub fn play(&mut self, name: &str, source: Decoder<BufReader<File>>) -> &mut Sink {
-		// First, check if the key exists
-		if !self.playing_sounds.contains_key(name) {
-			let (stream, stream_handle) = OutputStream::try_default().unwrap();
-			let new_sink = match Sink::try_new(&stream_handle){
-			//let new_sink = match Sink::try_new(&self.stream_handle){
-				Ok(handle)=>handle,
-				Err(why) => {
-					panic!("{}",why);
-				}
-			};
-			self.playing_sounds.insert(name.to_string(), new_sink);
-			self.stream_handles.insert(name.to_string(), (stream, stream_handle));
+	pub fn play_asset(&mut self, name: &str, bus: Bus, id: &str) -> Result<(), String> {
+		//plays a preloaded sound under the given sound name
+		self.play_asset_at_volume(name, bus, id, 1.0)
+	}
+
+	pub fn play_asset_at_volume(&mut self, name: &str, bus: Bus, id: &str, voice_volume: f32) -> Result<(), String> {
+		let sample = self.samples.get(id).ok_or(format!("Sound {} has not been loaded", id))?.clone();
+		self.play_at_volume(name, bus, sample, voice_volume);
+		Ok(())
+	}
+
+	pub fn play_sfx(&mut self, id: &str) -> Result<(), String> {
+		self.play_asset(id, Bus::Sfx, id)
+	}
+
+	pub fn play_sfx_at_volume(&mut self, id: &str, voice_volume: f32) -> Result<(), String> {
+		self.play_asset_at_volume(id, Bus::Sfx, id, voice_volume)
+	}
+
+	pub fn play_at_volume<S>(&mut self, name: &str, bus: Bus, source: S, voice_volume: f32)
+	where
+		S: Source + Send + 'static,
+		f32: FromSample<S::Item>,
+		S::Item: Sample + Send,
+	{
+		//starts another voice of this sound, cutting off an older one if it's at its voice limit
+		//voice_volume only applies to this voice, on top of the sound's volume and its bus
+		self.reclaim_finished();
+		let limit = self.voice_limit(name, bus);
+		let sink = self.open_sink();
+		let bus_settings = self.buses[&bus];
+		let sound = self.playing_sounds.entry(name.to_string())
+			.or_insert(PlayingSound{ voices: Vec::new(), bus, volume: 1.0 });
+		sound.bus = bus;
+
+		while !sound.voices.is_empty() && sound.voices.len() >= limit.max_voices.max(1) {
+			let victim = steal_voice(&sound.voices, limit.steal);
+			sound.voices.remove(victim).sink.stop();
 		}
 
-		let sink_obj = self.playing_sounds.get_mut(name).unwrap();
-		if !sink_obj.empty() {
-			sink_obj.clear();
+		sink.set_volume(mixed_volume(sound.volume * voice_volume, &bus_settings));
+		if self.output.is_some() {
+			sink.append(source);
+			sink.play();
+		}
+		self.voices_started += 1;
+		sound.voices.push(Voice{ sink, volume: voice_volume, started: self.voices_started });
+	}
+
+	pub fn queue<S>(&mut self, name: &str, source: S) -> Result<(), String>
+	where
+		S: Source + Send + 'static,
+		f32: FromSample<S::Item>,
+		S::Item: Sample + Send,
+	{
+		//plays the source after whatever the newest voice of this sound is already playing
+		let sound = self.playing_sounds.get(name).ok_or(format!("Sound {} is not playing", name))?;
+		let voice = sound.voices.last().ok_or(format!("Sound {} is not playing", name))?;
+		if self.output.is_some() {
+			voice.sink.append(source);
+		}
+		Ok(())
+	}
+
+	pub fn queued(&self, name: &str) -> usize {
+		//how many sources the newest voice of this sound has left to play, including the current one
+		self.get(name).map(|sink| sink.len()).unwrap_or(0)
+	}
+
+	pub fn set_voice_limit(&mut self, name: &str, limit: VoiceLimit) {
+		self.voice_limits.insert(name.to_string(), limit);
+	}
+
+	fn voice_limit(&self, name: &str, bus: Bus) -> VoiceLimit {
+		self.voice_limits.get(name).copied().unwrap_or(VoiceLimit::for_bus(bus))
+	}
+
+	pub fn stop(&mut self, name: &str) {
+		if let Some(sound) = self.playing_sounds.get_mut(name) {
+			for voice in sound.voices.drain(..) {
+				voice.sink.stop();
+			}
+		}
+	}
+
+	fn open_sink(&self) -> Sink {
+		let opened = self.output.as_ref().map(|(_, stream_handle)| Sink::try_new(stream_handle));
+		match opened {
+			Some(Ok(sink)) => sink,
+			Some(Err(why)) => {
+				println!("Unable to create sound sink: {}", why);
+				Sink::new_idle().0
+			},
+			None => Sink::new_idle().0
+		}
+	}
+
+	pub fn set_volume(&mut self, name: &str, volume: f32) {
+		//volume for every voice of a sound, still scaled by its bus
+		if let Some(sound) = self.playing_sounds.get_mut(name) {
+			sound.volume = volume;
+			let bus_settings = self.buses[&sound.bus];
+			for voice in &sound.voices {
+				voice.sink.set_volume(mixed_volume(volume * voice.volume, &bus_settings));
+			}
+		}
+	}
+
+	pub fn bus(&self, bus: Bus) -> BusSettings {
+		self.buses[&bus]
+	}
+
+	pub fn set_bus_volume(&mut self, bus: Bus, volume: f32) {
+		self.buses.get_mut(&bus).unwrap().volume = volume;
+		self.apply_bus(bus);
+	}
+
+	pub fn set_bus_muted(&mut self, bus: Bus, muted: bool) {
+		self.buses.get_mut(&bus).unwrap().muted = muted;
+		self.apply_bus(bus);
+	}
+
+	fn apply_bus(&mut self, bus: Bus) {
+		let bus_settings = self.buses[&bus];
+		for sound in self.playing_sounds.values().filter(|sound| sound.bus == bus) {
+			for voice in &sound.voices {
+				voice.sink.set_volume(mixed_volume(sound.volume * voice.volume, &bus_settings));
+			}
 		}
-		sink_obj.append(source);
-		sink_obj.play();
-		sink_obj
 	}
 
 	pub fn get(&self, name: &str) -> Option<&Sink> {
-		self.playing_sounds.get(name)
-	}
-	pub fn get_mut(&mut self, name: &str) -> Option<&mut Sink> {
-		//used if the player needs direct, mutable access to the sink object
-		self.playing_sounds.get_mut(name)
-	}
-	pub fn cleanup(&mut self) {
-		//remove all sinks and handles for empty sinks
-		//call this periodically to mitigate memory leaks
-		//if we had an automatic cleanup callback when sounds end, we wouldn't need this
-		let sounds_to_remove: Vec<String> = self.playing_sounds
-			.iter()
-			.filter(|(_, sink)| sink.empty())
-			.map(|(name, _)| name.clone())
-			.collect();
-		for name in sounds_to_remove {
-			self.playing_sounds.remove(&name);
-			self.stream_handles.remove(&name);
+		//the most recently started voice of a sound
+		self.playing_sounds.get(name).and_then(|sound| sound.voices.last()).map(|voice| &voice.sink)
+	}
+	fn reclaim_finished(&mut self) {
+		//drops the sinks of voices that have finished playing, runs every time a sound is played
+		//sounds left with no voices are forgotten too, so their volume is back to 1.0 the next time they play
+		for sound in self.playing_sounds.values_mut() {
+			sound.voices.retain(|voice| !voice.sink.empty());
 		}
+		self.playing_sounds.retain(|_, sound| !sound.voices.is_empty());
+	}
+
+}
+
+fn steal_voice(voices: &[Voice], steal: StealPolicy) -> usize {
+	//index of the voice to cut off, ties go to the oldest
+	let oldest = |a: &(usize, &Voice), b: &(usize, &Voice)| a.1.started.cmp(&b.1.started);
+	let chosen = match steal {
+		StealPolicy::Oldest => voices.iter().enumerate().min_by(oldest),
+		StealPolicy::Quietest => voices.iter().enumerate().min_by(|a, b| {
+			a.1.volume.partial_cmp(&b.1.volume)
+				.unwrap_or(std::cmp::Ordering::Equal)
+				.then_with(|| oldest(a, b))
+		}),
+	};
+	chosen.map(|(index, _)| index).unwrap_or(0)
+}
+
+fn mixed_volume(volume: f32, bus_settings: &BusSettings) -> f32 {
+	if bus_settings.muted {
+		0.0
+	} else {
+		volume * bus_settings.volume
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn voice(volume: f32, started: u64) -> Voice {
+		Voice{ sink: Sink::new_idle().0, volume, started }
 	}
 
+	#[test]
+	fn steals_the_quietest_voice_then_the_oldest() {
+		let voices = [voice(1.0, 1), voice(0.4, 2), voice(0.4, 3), voice(0.8, 4)];
+		assert_eq!(steal_voice(&voices, StealPolicy::Quietest), 1);
+		assert_eq!(steal_voice(&voices, StealPolicy::Oldest), 0);
+	}
+
+	#[test]
+	fn forgets_sounds_once_their_voices_finish() {
+		//headless voices never get anything to play, so they count as finished straight away
+		let mut sound_manager = SoundManager::headless();
+		sound_manager.play_at_volume("first", Bus::Sfx, rodio::source::Empty::<f32>::new(), 1.0);
+		assert!(sound_manager.get("first").is_some());
+		sound_manager.play_at_volume("second", Bus::Sfx, rodio::source::Empty::<f32>::new(), 1.0);
+		assert!(sound_manager.get("first").is_none());
+		assert_eq!(sound_manager.playing_sounds.len(), 1);
+	}
 }
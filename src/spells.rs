@@ -0,0 +1,92 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use serde::Deserialize;
+use crate::combat::{Status, Target};
+
+// Spell definitions loaded from assets/data/spells.toml, and the projectiles they fire.
+// Every spell is a projectile: a burst around the caster is a projectile that doesn't move and only lives a tick.
+
+pub const MAX_PROJECTILES: usize = 32;
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct Spell{
+	pub mana_cost: f32,
+	pub cast_ticks: usize,
+	pub speed: f32, //pixels per tick
+	pub lifetime: usize, //ticks before it fizzles out if it doesn't hit anything
+	pub radius: u32, //size of the projectile itself
+	#[serde(default)]
+	pub area: u32, //radius of the explosion when it hits, 0 to only hit what it touched
+	pub damage: i32,
+	pub knockback_ticks: usize,
+	pub status: Option<Status>,
+	pub color: [u8; 3],
+}
+
+#[derive(Deserialize)]
+struct SpellsFile{
+	spells: BTreeMap<String, Spell>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct SpellId(usize);
+
+#[derive(Debug)]
+pub struct Spells{
+	spells: Vec<Spell>,
+	names: Vec<String>,
+	ids: HashMap<String, SpellId>,
+}
+
+impl Spells{
+	pub fn load(path: &str) -> Result<Spells, String>{
+		let text = fs::read_to_string(path).map_err(|why| format!("Unable to read spells {}: {}", path, why))?;
+		let file: SpellsFile = toml::from_str(&text).map_err(|why| format!("Unable to parse spells {}: {}", path, why))?;
+		let mut spells = Spells{ spells: Vec::new(), names: Vec::new(), ids: HashMap::new() };
+		for (name, spell) in file.spells {
+			spells.ids.insert(name.clone(), SpellId(spells.spells.len()));
+			spells.names.push(name);
+			spells.spells.push(spell);
+		}
+		Ok(spells)
+	}
+
+	pub fn id(&self, name: &str) -> Option<SpellId>{
+		self.ids.get(name).copied()
+	}
+
+	pub fn get(&self, id: SpellId) -> &Spell{
+		&self.spells[id.0]
+	}
+
+	pub fn name(&self, id: SpellId) -> &str{
+		&self.names[id.0]
+	}
+
+	pub fn named(&self) -> impl Iterator<Item = (&String, &Spell)>{
+		self.names.iter().zip(&self.spells)
+	}
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Projectile{
+	pub spell: SpellId,
+	pub owner: Target, //only hits the other side
+	pub x: f32,
+	pub y: f32,
+	pub angle: f32, //same convention as facing_vector
+	pub ticks_left: usize,
+}
+
+impl Projectile{
+	pub fn new(spell: SpellId, spells: &Spells, owner: Target, x: f32, y: f32, angle: f32) -> Projectile{
+		Projectile{ spell, owner, x, y, angle, ticks_left: spells.get(spell).lifetime }
+	}
+
+	pub fn advance(&mut self, spells: &Spells){
+		let speed = spells.get(self.spell).speed;
+		self.x += self.angle.cos() * speed;
+		self.y -= self.angle.sin() * speed;
+		self.ticks_left = self.ticks_left.saturating_sub(1);
+	}
+}
@@ -0,0 +1,167 @@
+use sdl2::pixels::Color;
+use sdl2::rect::{Point, Rect};
+use sdl2::render::WindowCanvas;
+use crate::assets::{AssetManager, FontHandle};
+
+// Lays text out inside a box instead of stretching it over one.
+// Lines are measured with the font, optionally word wrapped to the box width, aligned,
+// and the whole block is scaled down evenly if it still doesn't fit so glyphs keep their shape.
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Align {
+	Left,
+	Center,
+	Right,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TextStyle {
+	pub font: FontHandle,
+	pub size: u16,
+	pub color: Color,
+	pub align: Align,
+	pub wrap: bool,
+	pub line_spacing: f32, //multiplier on the font's recommended line spacing
+	pub shadow: Option<(Point, Color)>, //offset and color
+	pub outline: Option<(u16, Color)>, //width in pixels and color
+}
+
+impl TextStyle {
+	pub fn new(font: FontHandle, size: u16, color: Color) -> TextStyle {
+		TextStyle {
+			font,
+			size,
+			color,
+			align: Align::Left,
+			wrap: false,
+			line_spacing: 1.0,
+			shadow: None,
+			outline: None,
+		}
+	}
+	pub fn align(mut self, align: Align) -> TextStyle {
+		self.align = align;
+		self
+	}
+	pub fn wrap(mut self) -> TextStyle {
+		self.wrap = true;
+		self
+	}
+	pub fn line_spacing(mut self, line_spacing: f32) -> TextStyle {
+		self.line_spacing = line_spacing;
+		self
+	}
+	pub fn shadow(mut self, offset: Point, color: Color) -> TextStyle {
+		self.shadow = Some((offset, color));
+		self
+	}
+	pub fn outline(mut self, width: u16, color: Color) -> TextStyle {
+		self.outline = Some((width, color));
+		self
+	}
+}
+
+struct Layout {
+	lines: Vec<(String, u32)>, //text and width
+	line_height: u32,
+	width: u32,
+	height: u32,
+}
+
+fn layout(assets: &mut AssetManager, style: &TextStyle, text: &str, max_width: Option<u32>) -> Result<Layout, String> {
+	let font = assets.sized_font(style.font, style.size, 0)?;
+	let measure = |line: &str| -> Result<u32, String> {
+		if line.is_empty() {
+			return Ok(0);
+		}
+		font.size_of(line).map(|(width, _)| width).map_err(|why| why.to_string())
+	};
+	let mut lines = Vec::new();
+	for paragraph in text.split('\n') {
+		let max_width = match max_width {
+			Some(max_width) if style.wrap => max_width,
+			_ => {
+				lines.push((paragraph.to_string(), measure(paragraph)?));
+				continue;
+			}
+		};
+		//greedy word wrap, a single word wider than the box gets a line to itself
+		let mut line = String::new();
+		for word in paragraph.split_whitespace() {
+			let candidate = if line.is_empty() { word.to_string() } else { format!("{} {}", line, word) };
+			if !line.is_empty() && measure(&candidate)? > max_width {
+				let width = measure(&line)?;
+				lines.push((line, width));
+				line = word.to_string();
+			} else {
+				line = candidate;
+			}
+		}
+		let width = measure(&line)?;
+		lines.push((line, width));
+	}
+	let line_height = (font.recommended_line_spacing().max(font.height()) as f32 * style.line_spacing).round().max(1.0) as u32;
+	let width = lines.iter().map(|(_, width)| *width).max().unwrap_or(0);
+	let height = line_height * (lines.len() as u32 - 1) + font.height().max(0) as u32;
+	Ok(Layout{ lines, line_height, width, height })
+}
+
+pub fn measure_text(assets: &mut AssetManager, style: &TextStyle, text: &str, max_width: Option<u32>) -> Result<(u32, u32), String> {
+	let layout = layout(assets, style, text, max_width)?;
+	Ok((layout.width, layout.height))
+}
+
+pub fn draw_text(canvas: &mut WindowCanvas, assets: &mut AssetManager, style: &TextStyle, text: &str, bounds: Rect) -> Result<Rect, String> {
+	//returns the area the text ended up covering
+	let layout = layout(assets, style, text, Some(bounds.width()))?;
+	if layout.width == 0 || layout.height == 0 {
+		return Ok(Rect::new(bounds.x(), bounds.y(), 0, 0));
+	}
+	let scale = (bounds.width() as f32 / layout.width as f32)
+		.min(bounds.height() as f32 / layout.height as f32)
+		.min(1.0);
+	let scaled = |value: i32| (value as f32 * scale).round() as i32;
+
+	let mut covered: Option<Rect> = None;
+	for (i, (line, line_width)) in layout.lines.iter().enumerate() {
+		if line.is_empty() {
+			continue;
+		}
+		let line_width = scaled(*line_width as i32);
+		let x = match style.align {
+			Align::Left => bounds.x(),
+			Align::Center => bounds.x() + (bounds.width() as i32 - line_width) / 2,
+			Align::Right => bounds.x() + bounds.width() as i32 - line_width,
+		};
+		let y = bounds.y() + scaled((layout.line_height * i as u32) as i32);
+		let origin = Point::new(x, y);
+
+		if let Some((offset, shadow_color)) = style.shadow {
+			let shadow_origin = origin.offset(scaled(offset.x()), scaled(offset.y()));
+			draw_line(canvas, assets, style, (0, shadow_color), line, shadow_origin, scale)?;
+		}
+		if let Some((width, outline_color)) = style.outline {
+			//outlined glyphs come out bigger by the outline width on every side
+			let outline_origin = origin.offset(-scaled(width as i32), -scaled(width as i32));
+			draw_line(canvas, assets, style, (width, outline_color), line, outline_origin, scale)?;
+		}
+		let drawn = draw_line(canvas, assets, style, (0, style.color), line, origin, scale)?;
+		covered = Some(covered.map(|covered| covered.union(drawn)).unwrap_or(drawn));
+	}
+	Ok(covered.unwrap_or(Rect::new(bounds.x(), bounds.y(), 0, 0)))
+}
+
+fn draw_line(canvas: &mut WindowCanvas, assets: &mut AssetManager, style: &TextStyle, pass: (u16, Color), line: &str, origin: Point, scale: f32) -> Result<Rect, String> {
+	//pass is the outline width and color to draw this copy of the line with
+	let (outline, color) = pass;
+	let texture = assets.outlined_text(style.font, style.size, outline, line, color)?;
+	let query = texture.query();
+	let target = Rect::new(
+		origin.x(),
+		origin.y(),
+		((query.width as f32 * scale).round() as u32).max(1),
+		((query.height as f32 * scale).round() as u32).max(1)
+	);
+	canvas.copy(texture, None, Some(target))?;
+	Ok(target)
+}
@@ -0,0 +1,98 @@
+use std::time::{Duration, Instant};
+
+pub struct FixedTimestep{
+	pub tick_duration: Duration,
+	pub max_ticks_per_frame: u32,
+	accumulator: Duration,
+	last_frame: Instant,
+	dropped: Duration, //time thrown away since the simulation last kept up, only logged when it catches up again
+}
+
+impl FixedTimestep{
+	pub fn new(ticks_per_second: u32, max_ticks_per_frame: u32) -> FixedTimestep{
+		FixedTimestep{
+			tick_duration: Duration::from_secs(1) / ticks_per_second,
+			max_ticks_per_frame,
+			accumulator: Duration::ZERO,
+			last_frame: Instant::now(),
+			dropped: Duration::ZERO,
+		}
+	}
+
+	pub fn begin_frame(&mut self){
+		//bank the real time that passed since the last frame so the simulation can catch up to it
+		let now = Instant::now();
+		self.bank(now - self.last_frame);
+		self.last_frame = now;
+	}
+
+	fn bank(&mut self, elapsed: Duration){
+		self.accumulator += elapsed;
+
+		//if we fell too far behind, throw the extra time away instead of trying to catch up
+		//otherwise long ticks cause more ticks the next frame and we never recover (spiral of death)
+		let max_backlog = self.tick_duration * self.max_ticks_per_frame;
+		//log once when it starts falling behind and once when it recovers rather than every frame in between
+		if self.accumulator > max_backlog {
+			if self.dropped.is_zero() {
+				println!("Simulation fell behind, dropping time until it catches up");
+			}
+			self.dropped += self.accumulator - max_backlog;
+			self.accumulator = max_backlog;
+		}else if !self.dropped.is_zero() {
+			println!("Simulation caught up, dropped {:?}", self.dropped);
+			self.dropped = Duration::ZERO;
+		}
+	}
+
+	pub fn consume_tick(&mut self) -> bool{
+		//call in a loop, runs true once for every whole tick that's banked up
+		if self.accumulator >= self.tick_duration {
+			self.accumulator -= self.tick_duration;
+			true
+		}else{
+			false
+		}
+	}
+
+	pub fn alpha(&self) -> f32{
+		//how far we are between the last tick and the next one, used to interpolate rendering
+		self.accumulator.as_secs_f32() / self.tick_duration.as_secs_f32()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn runs_one_tick_per_banked_tick(){
+		let mut timestep = FixedTimestep::new(50, 5);
+		timestep.bank(Duration::from_millis(40));
+		let mut ticks = 0;
+		while timestep.consume_tick() {
+			ticks += 1;
+		}
+		assert_eq!(ticks, 2);
+		assert!(timestep.alpha() < 0.01);
+		timestep.bank(Duration::from_millis(10));
+		assert!(!timestep.consume_tick());
+		assert!((timestep.alpha() - 0.5).abs() < 0.01);
+	}
+
+	#[test]
+	fn drops_time_past_the_max_backlog(){
+		let mut timestep = FixedTimestep::new(50, 3);
+		timestep.bank(Duration::from_secs(2));
+		let mut ticks = 0;
+		while timestep.consume_tick() {
+			ticks += 1;
+		}
+		assert_eq!(ticks, 3);
+		assert_eq!(timestep.dropped, Duration::from_millis(1940));
+		//keeping up again clears it
+		timestep.bank(Duration::from_millis(20));
+		assert_eq!(timestep.dropped, Duration::ZERO);
+		assert!(timestep.consume_tick());
+	}
+}
@@ -0,0 +1,143 @@
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::Keycode;
+use sdl2::render::WindowCanvas;
+use sdl2::sys::SDL_RendererFlags;
+use sdl2::video::{FullscreenType, Window};
+use sdl2::VideoSubsystem;
+use serde::{Deserialize, Serialize};
+
+// Everything is drawn at a fixed logical resolution, SDL scales it up to the window and letterboxes the rest.
+// Screens should only ever think in logical pixels.
+pub const LOGICAL_WIDTH: u32 = 1080;
+pub const LOGICAL_HEIGHT: u32 = 720;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum DisplayMode{
+	Windowed,
+	Borderless, //fullscreen at the desktop resolution
+	Fullscreen, //exclusive fullscreen, changes the display mode
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum Scaling{
+	Integer, //whole multiples of the logical resolution only, crisp pixels but bigger bars
+	Aspect, //as big as fits while keeping the aspect ratio
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum RendererBackend{
+	Accelerated, //GPU rendering, falls back to software if the driver can't do it
+	Software,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(default)]
+pub struct VideoSettings{
+	pub window_width: u32,
+	pub window_height: u32,
+	pub display_mode: DisplayMode,
+	pub scaling: Scaling,
+	pub renderer: RendererBackend,
+	pub vsync: bool, //only used by the accelerated renderer
+}
+
+impl Default for VideoSettings{
+	fn default() -> VideoSettings{
+		VideoSettings{
+			window_width: LOGICAL_WIDTH,
+			window_height: LOGICAL_HEIGHT,
+			display_mode: DisplayMode::Windowed,
+			scaling: Scaling::Aspect,
+			renderer: RendererBackend::Accelerated,
+			vsync: true,
+		}
+	}
+}
+
+// What we actually ended up with, which isn't always what the settings asked for
+#[derive(Clone, Copy, Debug)]
+pub struct ActiveRenderer{
+	pub vsync: bool,
+}
+
+fn build_window(video_subsystem: &VideoSubsystem, video: &VideoSettings) -> Result<Window, String>{
+	video_subsystem.window("Game Window", video.window_width, video.window_height)
+		.position_centered()
+		.resizable()
+		.build()
+		.map_err(|why| format!("Failed to create window: {}", why))
+}
+
+pub fn create_canvas(video_subsystem: &VideoSubsystem, video: &VideoSettings) -> Result<(WindowCanvas, ActiveRenderer), String>{
+	if video.renderer == RendererBackend::Accelerated {
+		let mut builder = build_window(video_subsystem, video)?.into_canvas().accelerated();
+		if video.vsync {
+			builder = builder.present_vsync();
+		}
+		match builder.build() {
+			//SDL will quietly hand back its software renderer if there's no usable driver, so check what we got
+			Ok(canvas) if canvas.info().flags & SDL_RendererFlags::SDL_RENDERER_ACCELERATED as u32 != 0 => {
+				let vsync = canvas.info().flags & SDL_RendererFlags::SDL_RENDERER_PRESENTVSYNC as u32 != 0;
+				println!("Using accelerated renderer {}, vsync {}", canvas.info().name, if vsync { "on" } else { "off" });
+				return Ok((canvas, ActiveRenderer{ vsync }));
+			},
+			Ok(canvas) => println!("Renderer {} isn't accelerated, falling back to software", canvas.info().name),
+			Err(why) => println!("Unable to create accelerated renderer, falling back to software: {}", why),
+		}
+	}
+	//building a canvas consumes the window, so a failed attempt needs a fresh one
+	let canvas = build_window(video_subsystem, video)?
+		.into_canvas()
+		.software()
+		.build()
+		.map_err(|why| format!("Failed to create canvas from window: {}", why))?;
+	println!("Using software renderer");
+	Ok((canvas, ActiveRenderer{ vsync: false }))
+}
+
+pub fn apply_video_settings(canvas: &mut WindowCanvas, video: &VideoSettings) -> Result<(), String>{
+	canvas.set_logical_size(LOGICAL_WIDTH, LOGICAL_HEIGHT).map_err(|why| why.to_string())?;
+	canvas.set_integer_scale(video.scaling == Scaling::Integer)?;
+	let fullscreen_type = match video.display_mode {
+		DisplayMode::Windowed => FullscreenType::Off,
+		DisplayMode::Borderless => FullscreenType::Desktop,
+		DisplayMode::Fullscreen => FullscreenType::True,
+	};
+	canvas.window_mut().set_fullscreen(fullscreen_type)?;
+	Ok(())
+}
+
+pub fn handle_window_event(canvas: &mut WindowCanvas, video: &mut VideoSettings, event: &Event) -> bool{
+	//window size and display mode hotkeys, returns true if the settings changed and should be saved
+	match event {
+		Event::KeyDown {keycode: Some(Keycode::F11), repeat: false, ..} => {
+			//cycle windowed -> borderless -> fullscreen
+			video.display_mode = match video.display_mode {
+				DisplayMode::Windowed => DisplayMode::Borderless,
+				DisplayMode::Borderless => DisplayMode::Fullscreen,
+				DisplayMode::Fullscreen => DisplayMode::Windowed,
+			};
+			if let Err(why) = apply_video_settings(canvas, video) {
+				println!("Unable to change display mode: {}", why);
+			}
+			true
+		},
+		Event::KeyDown {keycode: Some(Keycode::F10), repeat: false, ..} => {
+			video.scaling = match video.scaling {
+				Scaling::Integer => Scaling::Aspect,
+				Scaling::Aspect => Scaling::Integer,
+			};
+			if let Err(why) = apply_video_settings(canvas, video) {
+				println!("Unable to change scaling: {}", why);
+			}
+			true
+		},
+		Event::Window {win_event: WindowEvent::Resized(width, height), ..} if video.display_mode == DisplayMode::Windowed => {
+			//remember the size the player dragged the window to, SDL already rescales the logical canvas for us
+			video.window_width = (*width).max(1) as u32;
+			video.window_height = (*height).max(1) as u32;
+			true
+		},
+		_ => false
+	}
+}